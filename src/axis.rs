@@ -0,0 +1,355 @@
+//! Typed axes emitting `chxt`, `chxl`, `chxp`, `chxr` and `chxs` with matching indices
+
+use std::fmt;
+
+use crate::data::format_number;
+use crate::{positional_fields, Color, ImageCharts, ImageChartsError, ImageChartsErrorKind};
+
+/// Axis label color the API defaults to
+const DEFAULT_LABEL_COLOR: &str = "666666";
+
+/// Axis label font size the API defaults to
+const DEFAULT_FONT_SIZE: &str = "11";
+
+/// Side of the chart an axis is drawn on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AxisSide {
+    /// Bottom axis (`x`)
+    Bottom,
+    /// Left axis (`y`)
+    Left,
+    /// Top axis (`t`)
+    Top,
+    /// Right axis (`r`)
+    Right,
+}
+
+impl AxisSide {
+    /// Value of this side in `chxt`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AxisSide::Bottom => "x",
+            AxisSide::Left => "y",
+            AxisSide::Top => "t",
+            AxisSide::Right => "r",
+        }
+    }
+
+    /// Label alignment the API uses for this side: labels face the axis
+    fn default_alignment(&self) -> i8 {
+        match self {
+            AxisSide::Bottom | AxisSide::Top => 0,
+            AxisSide::Left => 1,
+            AxisSide::Right => -1,
+        }
+    }
+}
+
+impl fmt::Display for AxisSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Which of the axis line and tick marks are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TickStyle {
+    /// Axis line only (`l`)
+    Line,
+    /// Tick marks only (`t`)
+    Ticks,
+    /// Axis line and tick marks (`lt`)
+    LineAndTicks,
+    /// Neither, labels only (`_`)
+    None,
+}
+
+impl TickStyle {
+    /// Value of this style in `chxs`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TickStyle::Line => "l",
+            TickStyle::Ticks => "t",
+            TickStyle::LineAndTicks => "lt",
+            TickStyle::None => "_",
+        }
+    }
+}
+
+/// One chart axis: side, labels, range and style
+///
+/// Added with [`ImageCharts::axis`], which appends the axis to `chxt` and writes its
+/// labels, label positions, range and style under the same index in `chxl`, `chxp`,
+/// `chxr` and `chxs`.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ Axis, Color, ImageCharts, TickStyle };
+///
+/// let chart = ImageCharts::new()
+///     .cht("lc")
+///     .chs("700x300")
+///     .axis(Axis::bottom().labels(["Jan", "Feb", "Mar"]).unwrap())
+///     .axis(
+///         Axis::left()
+///             .range_with_step(0.0, 1000.0, 250.0)
+///             .format("N*cUSD*")
+///             .color(Color::rgb(0xFF, 0, 0))
+///             .font_size(12)
+///             .tick_style(TickStyle::LineAndTicks),
+///     );
+///
+/// let url = chart.to_url();
+/// assert!(url.contains("chxt=x%2Cy"));
+/// assert!(url.contains("chxl=0%3A%7CJan%7CFeb%7CMar"));
+/// assert!(url.contains("chxr=1%2C0%2C1000%2C250"));
+/// assert!(url.contains("chxs=1N%2AcUSD%2A%2CFF0000%2C12%2C1%2Clt"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axis {
+    side: AxisSide,
+    labels: Vec<String>,
+    positions: Vec<f64>,
+    range: Option<(f64, f64, Option<f64>)>,
+    format: Option<String>,
+    color: Option<Color>,
+    font_size: Option<u32>,
+    tick_style: Option<TickStyle>,
+}
+
+impl Axis {
+    /// Axis on `side`, labelled by the API from the data range
+    pub fn new(side: AxisSide) -> Self {
+        Self {
+            side,
+            labels: Vec::new(),
+            positions: Vec::new(),
+            range: None,
+            format: None,
+            color: None,
+            font_size: None,
+            tick_style: None,
+        }
+    }
+
+    /// Bottom axis (`x`)
+    pub fn bottom() -> Self {
+        Self::new(AxisSide::Bottom)
+    }
+
+    /// Left axis (`y`)
+    pub fn left() -> Self {
+        Self::new(AxisSide::Left)
+    }
+
+    /// Top axis (`t`)
+    pub fn top() -> Self {
+        Self::new(AxisSide::Top)
+    }
+
+    /// Right axis (`r`)
+    pub fn right() -> Self {
+        Self::new(AxisSide::Right)
+    }
+
+    /// Custom labels, evenly spaced unless [`Axis::positions`] is set
+    ///
+    /// Fails with [`ImageChartsErrorKind::InvalidConfig`] when a label contains `|`.
+    pub fn labels<I, S>(mut self, labels: I) -> Result<Self, ImageChartsError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.labels = labels.into_iter().map(Into::into).collect();
+        if let Some(label) = self.labels.iter().find(|label| label.contains('|')) {
+            return Err(ImageChartsError::new(
+                ImageChartsErrorKind::InvalidConfig,
+                format!(
+                    "Axis label \"{}\" contains \"|\", the \"chxl\" label separator",
+                    label
+                ),
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Position of each label along the axis, in the units of the axis range
+    pub fn positions(mut self, positions: impl IntoIterator<Item = f64>) -> Self {
+        self.positions = positions.into_iter().collect();
+        self
+    }
+
+    /// Values shown from the start to the end of the axis
+    pub fn range(mut self, start: f64, end: f64) -> Self {
+        self.range = Some((start, end, None));
+        self
+    }
+
+    /// Values shown from the start to the end of the axis, one label every `step`
+    pub fn range_with_step(mut self, start: f64, end: f64, step: f64) -> Self {
+        self.range = Some((start, end, Some(step)));
+        self
+    }
+
+    /// Number format of the labels, such as `N*f1*` or `N*cUSD*`
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+
+    /// Color of the labels
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Font size of the labels, in pixels
+    pub fn font_size(mut self, size: u32) -> Self {
+        self.font_size = Some(size);
+        self
+    }
+
+    /// Which of the axis line and tick marks are drawn
+    pub fn tick_style(mut self, style: TickStyle) -> Self {
+        self.tick_style = Some(style);
+        self
+    }
+
+    fn chxl(&self, index: usize) -> Option<String> {
+        if self.labels.is_empty() {
+            return None;
+        }
+        Some(format!("{}:|{}", index, self.labels.join("|")))
+    }
+
+    fn chxp(&self, index: usize) -> Option<String> {
+        if self.positions.is_empty() {
+            return None;
+        }
+        let positions: Vec<String> = self
+            .positions
+            .iter()
+            .map(|position| format_number(*position, None))
+            .collect();
+        Some(format!("{},{}", index, positions.join(",")))
+    }
+
+    fn chxr(&self, index: usize) -> Option<String> {
+        let (start, end, step) = self.range?;
+        let mut chxr = format!(
+            "{},{},{}",
+            index,
+            format_number(start, None),
+            format_number(end, None)
+        );
+        if let Some(step) = step {
+            chxr.push_str(&format!(",{}", format_number(step, None)));
+        }
+        Some(chxr)
+    }
+
+    fn chxs(&self, index: usize) -> Option<String> {
+        let index = index.to_string();
+        let alignment = self.side.default_alignment().to_string();
+        positional_fields([
+            (
+                self.format
+                    .as_ref()
+                    .map(|format| format!("{}{}", index, format)),
+                &index,
+            ),
+            (
+                self.color.map(|color| color.to_string()),
+                DEFAULT_LABEL_COLOR,
+            ),
+            (
+                self.font_size.map(|size| size.to_string()),
+                DEFAULT_FONT_SIZE,
+            ),
+            (None, &alignment),
+            (self.tick_style.map(|style| style.as_str().to_string()), ""),
+        ])
+    }
+}
+
+impl ImageCharts {
+    /// Add an axis, keeping `chxt`, `chxl`, `chxp`, `chxr` and `chxs` indices consistent
+    ///
+    /// The axis gets the next index in `chxt`, axes declared with [`ImageCharts::chxt`]
+    /// included, and each parameter it needs gets an entry under that index.
+    pub fn axis(self, axis: Axis) -> Self {
+        let index = self
+            .query
+            .get("chxt")
+            .filter(|chxt| !chxt.is_empty())
+            .map_or(0, |chxt| chxt.split(',').count());
+        let chart = self.appended("chxt", ",", Some(axis.side.to_string()));
+        let chart = chart.appended("chxl", "|", axis.chxl(index));
+        let chart = chart.appended("chxp", "|", axis.chxp(index));
+        let chart = chart.appended("chxr", "|", axis.chxr(index));
+        chart.appended("chxs", "|", axis.chxs(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_axes_share_indices() {
+        let chart = ImageCharts::new()
+            .axis(
+                Axis::bottom()
+                    .labels(["Q1", "Q2"])
+                    .unwrap()
+                    .positions([0.0, 50.5]),
+            )
+            .axis(Axis::left().range(0.0, 100.0))
+            .axis(
+                Axis::right()
+                    .labels(["low", "high"])
+                    .unwrap()
+                    .color(Color::BLACK),
+            )
+            .axis(Axis::top());
+        assert_eq!(chart.query["chxt"], "x,y,r,t");
+        assert_eq!(chart.query["chxl"], "0:|Q1|Q2|2:|low|high");
+        assert_eq!(chart.query["chxp"], "0,0,50.5");
+        assert_eq!(chart.query["chxr"], "1,0,100");
+        assert_eq!(chart.query["chxs"], "2,000000");
+    }
+
+    #[test]
+    fn test_labels_reject_separator() {
+        let err = Axis::bottom().labels(["Q1|Q2", "Q3"]).unwrap_err();
+        assert_eq!(err.kind, ImageChartsErrorKind::InvalidConfig);
+        assert_eq!(
+            err.message,
+            "Axis label \"Q1|Q2\" contains \"|\", the \"chxl\" label separator"
+        );
+    }
+
+    #[test]
+    fn test_axis_follows_manual_chxt() {
+        let chart = ImageCharts::new()
+            .chxt("x,y")
+            .chxr("1,0,10")
+            .axis(Axis::right().range_with_step(-5.0, 5.0, 2.5));
+        assert_eq!(chart.query["chxt"], "x,y,r");
+        assert_eq!(chart.query["chxr"], "1,0,10|2,-5,5,2.5");
+        assert!(!chart.query.contains_key("chxs"));
+    }
+
+    #[test]
+    fn test_tick_style_keeps_side_alignment() {
+        let chart = ImageCharts::new()
+            .axis(Axis::left().tick_style(TickStyle::Ticks))
+            .axis(Axis::right().format("N*p*").tick_style(TickStyle::None))
+            .axis(Axis::top().font_size(9));
+        assert_eq!(
+            chart.query["chxs"],
+            "0,666666,11,1,t|1N*p*,666666,11,-1,_|2,666666,9"
+        );
+    }
+}
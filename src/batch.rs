@@ -0,0 +1,246 @@
+//! Batch rendering of many charts over one client, with bounded concurrency
+
+#[cfg(feature = "blocking")]
+use crate::BlockingImageChartsClient;
+#[cfg(feature = "async")]
+use crate::ImageChartsClient;
+use crate::{ImageCharts, ImageChartsError};
+
+/// Result of each chart of a batch, in input order
+fn in_input_order(
+    mut results: Vec<(usize, Result<Vec<u8>, ImageChartsError>)>,
+) -> Vec<Result<Vec<u8>, ImageChartsError>> {
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(feature = "async")]
+impl ImageChartsClient {
+    /// Render every chart, at most `concurrency` at a time, and return the image of each
+    /// one in input order
+    ///
+    /// Charts are rendered by tasks spawned on the current tokio runtime, sharing the
+    /// connection pool, retry policy and configuration of this client. A failed chart
+    /// does not stop the others. A `concurrency` of 0 is treated as 1.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use image_charts::{ ImageCharts, ImageChartsClient };
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = ImageChartsClient::new()?;
+    ///     let charts = (1..=300).map(|i| {
+    ///         ImageCharts::new().cht("p").chd(format!("t:{},40", i)).chs("100x100")
+    ///     });
+    ///
+    ///     for (i, result) in client.to_buffers(charts, 8).await.into_iter().enumerate() {
+    ///         match result {
+    ///             Ok(image) => println!("chart {}: {} bytes", i, image.len()),
+    ///             Err(err) => eprintln!("chart {}: {}", i, err),
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn to_buffers<I>(
+        &self,
+        charts: I,
+        concurrency: usize,
+    ) -> Vec<Result<Vec<u8>, ImageChartsError>>
+    where
+        I: IntoIterator<Item = ImageCharts>,
+    {
+        let mut charts = charts.into_iter().enumerate();
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut results = Vec::new();
+        loop {
+            while tasks.len() < concurrency.max(1) {
+                let Some((index, chart)) = charts.next() else {
+                    break;
+                };
+                let client = self.clone();
+                tasks.spawn(async move { (index, client.to_buffer(&chart).await) });
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            results.push(joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic())));
+        }
+        in_input_order(results)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl BlockingImageChartsClient {
+    /// Render every chart on a pool of up to `concurrency` threads and return the image
+    /// of each one in input order
+    ///
+    /// Blocking counterpart of `ImageChartsClient::to_buffers`: the threads share the
+    /// connection pool of this client and exit once every chart is rendered. No more
+    /// threads than charts are spawned. A failed chart does not stop the others. A
+    /// `concurrency` of 0 is treated as 1.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use image_charts::{ BlockingImageChartsClient, ImageCharts };
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = BlockingImageChartsClient::new()?;
+    ///     let charts = ["100x100", "200x200", "300x300"]
+    ///         .map(|size| ImageCharts::new().cht("p").chd("t:60,40").chs(size));
+    ///
+    ///     let images = client.to_buffers(charts, 2);
+    ///     println!("{} charts rendered", images.iter().filter(|r| r.is_ok()).count());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn to_buffers<I>(
+        &self,
+        charts: I,
+        concurrency: usize,
+    ) -> Vec<Result<Vec<u8>, ImageChartsError>>
+    where
+        I: IntoIterator<Item = ImageCharts>,
+    {
+        let charts = charts.into_iter().collect::<Vec<_>>();
+        let threads = concurrency.max(1).min(charts.len());
+        let charts = std::sync::Mutex::new(charts.into_iter().enumerate());
+        let results = std::sync::Mutex::new(Vec::new());
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let next = charts.lock().unwrap_or_else(|e| e.into_inner()).next();
+                    let Some((index, chart)) = next else {
+                        break;
+                    };
+                    let result = self.to_buffer(&chart);
+                    results
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push((index, result));
+                });
+            }
+        });
+        in_input_order(results.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::mock::MockResponse;
+    use crate::transport::TransportRequest;
+    use crate::ImageChartsConfig;
+
+    /// Echoes the `chd` of each chart after a short delay, tracking concurrent requests
+    #[derive(Default)]
+    struct Echo {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl Echo {
+        fn respond(
+            &self,
+            request: &TransportRequest,
+        ) -> Result<crate::TransportResponse, ImageChartsError> {
+            let chart = ImageCharts::from_url(&request.url)?;
+            let chd = chart.query.get("chd").cloned().unwrap_or_default();
+            let response = if chd.contains("fail") {
+                MockResponse::error(400, "IC_INVALID_DATA")
+            } else {
+                MockResponse::new(200).body(chd)
+            };
+            response.into_response()
+        }
+
+        fn enter(&self) {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        }
+
+        fn leave(&self) {
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn charts() -> Vec<ImageCharts> {
+        ["t:1", "t:2", "t:fail", "t:4", "t:5", "t:6", "t:7"]
+            .into_iter()
+            .map(|chd| ImageCharts::new().cht("p").chs("100x100").chd(chd))
+            .collect()
+    }
+
+    fn assert_ordered(results: Vec<Result<Vec<u8>, ImageChartsError>>) {
+        let results = results
+            .into_iter()
+            .map(|result| result.map_err(|e| e.code.unwrap_or_default()))
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 7);
+        assert_eq!(results[0], Ok(b"t:1".to_vec()));
+        assert_eq!(results[2], Err("IC_INVALID_DATA".to_string()));
+        assert_eq!(results[6], Ok(b"t:7".to_vec()));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_to_buffers_keeps_order_and_limit() {
+        #[derive(Clone, Default)]
+        struct AsyncEcho(Arc<Echo>);
+
+        impl crate::Transport for AsyncEcho {
+            fn send(
+                &self,
+                request: TransportRequest,
+            ) -> crate::BoxFuture<'_, Result<crate::TransportResponse, ImageChartsError>>
+            {
+                Box::pin(async move {
+                    self.0.enter();
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    self.0.leave();
+                    self.0.respond(&request)
+                })
+            }
+        }
+
+        let echo = AsyncEcho::default();
+        let client = ImageChartsClient::with_transport(ImageChartsConfig::default(), echo.clone());
+        assert_ordered(client.to_buffers(charts(), 3).await);
+        assert_eq!(echo.0.max_in_flight.load(Ordering::SeqCst), 3);
+        assert!(client.to_buffers(Vec::new(), 0).await.is_empty());
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_to_buffers_keeps_order_and_limit() {
+        #[derive(Clone, Default)]
+        struct BlockingEcho(Arc<Echo>);
+
+        impl crate::BlockingTransport for BlockingEcho {
+            fn send(
+                &self,
+                request: TransportRequest,
+            ) -> Result<crate::TransportResponse, ImageChartsError> {
+                self.0.enter();
+                std::thread::sleep(Duration::from_millis(10));
+                self.0.leave();
+                self.0.respond(&request)
+            }
+        }
+
+        let echo = BlockingEcho::default();
+        let client =
+            BlockingImageChartsClient::with_transport(ImageChartsConfig::default(), echo.clone());
+        assert_ordered(client.to_buffers(charts(), 3));
+        assert!(echo.0.max_in_flight.load(Ordering::SeqCst) <= 3);
+        assert_ordered(client.to_buffers(charts(), 0));
+        assert!(client.to_buffers(Vec::new(), 8).is_empty());
+    }
+}
@@ -0,0 +1,429 @@
+//! Caches of rendered charts, keyed by canonical chart URL
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use sha2::{Digest, Sha256};
+
+use crate::OutputFormat;
+
+/// Rendered chart stored in a [`ChartCache`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedChart {
+    /// Image bytes
+    pub bytes: Vec<u8>,
+    /// Format announced by the response `Content-Type`, if any
+    pub format: Option<OutputFormat>,
+}
+
+/// Storage of rendered charts consulted before any request
+///
+/// Keys are chart URLs as built by [`ImageCharts::to_url`](crate::ImageCharts::to_url):
+/// parameters are sorted, so identical charts share one entry. Caches are best-effort,
+/// failing to read or write an entry must behave as a miss rather than an error.
+///
+/// The download methods returning bytes (`to_buffer`, `to_data_uri`) fill the cache;
+/// streaming ones (`to_writer`, `to_async_writer`, `to_file`) are served from it but do
+/// not keep the images they stream.
+pub trait ChartCache: fmt::Debug + Send + Sync {
+    /// Chart stored under `url`, if any and still fresh
+    fn get(&self, url: &str) -> Option<CachedChart>;
+
+    /// Store `chart` under `url`
+    fn put(&self, url: &str, chart: CachedChart);
+}
+
+#[derive(Debug)]
+struct MemoryEntry {
+    chart: CachedChart,
+    stored: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    entries: HashMap<String, MemoryEntry>,
+    /// Keys by last use, least recently used first
+    usage: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl MemoryState {
+    fn touch(&mut self, url: &str) -> Option<&MemoryEntry> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(url)?;
+        self.usage.remove(&entry.last_used);
+        entry.last_used = self.clock;
+        self.usage.insert(self.clock, url.to_string());
+        Some(entry)
+    }
+
+    fn remove(&mut self, url: &str) {
+        if let Some(entry) = self.entries.remove(url) {
+            self.usage.remove(&entry.last_used);
+        }
+    }
+}
+
+/// In-memory cache evicting the least recently used chart beyond `capacity` charts
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ ImageCharts, MemoryCache };
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let cache = Arc::new(MemoryCache::new(500).ttl(Duration::from_secs(3600)));
+/// let chart = ImageCharts::builder().cache(cache).build();
+/// ```
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    ttl: Option<Duration>,
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryCache {
+    /// Cache holding up to `capacity` charts
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ttl: None,
+            state: Mutex::new(MemoryState::default()),
+        }
+    }
+
+    /// Expire charts `ttl` after they were stored
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Number of charts currently stored, expired ones included
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Whether no chart is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ChartCache for MemoryCache {
+    fn get(&self, url: &str) -> Option<CachedChart> {
+        let mut state = self.lock();
+        let expired = {
+            let entry = state.touch(url)?;
+            self.ttl.is_some_and(|ttl| entry.stored.elapsed() >= ttl)
+        };
+        if expired {
+            state.remove(url);
+            return None;
+        }
+        state.entries.get(url).map(|entry| entry.chart.clone())
+    }
+
+    fn put(&self, url: &str, chart: CachedChart) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.lock();
+        state.remove(url);
+        while state.entries.len() >= self.capacity {
+            let Some((_, oldest)) = state.usage.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+        state.clock += 1;
+        let last_used = state.clock;
+        state.usage.insert(last_used, url.to_string());
+        state.entries.insert(
+            url.to_string(),
+            MemoryEntry {
+                chart,
+                stored: Instant::now(),
+                last_used,
+            },
+        );
+    }
+}
+
+/// Extension of cached files whose format is unknown
+const UNKNOWN_EXTENSION: &str = "bin";
+
+/// Filesystem cache storing each chart in `<sha256 of the URL>.<extension>`
+///
+/// Charts older than the TTL are ignored and deleted. Once the directory grows beyond
+/// the maximum size, the oldest charts are deleted first. Entries are written to a
+/// temporary file then renamed, so concurrent processes can share the directory.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use image_charts::{ FileCache, ImageCharts };
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// fn main() -> std::io::Result<()> {
+///     let cache = FileCache::new("/var/cache/charts")?
+///         .ttl(Duration::from_secs(24 * 3600))
+///         .max_size(512 * 1024 * 1024);
+///     let chart = ImageCharts::builder().cache(Arc::new(cache)).build();
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FileCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    max_size: Option<u64>,
+}
+
+impl FileCache {
+    /// Cache storing charts in `dir`, created if missing
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            ttl: None,
+            max_size: None,
+        })
+    }
+
+    /// Expire charts `ttl` after they were stored
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Keep the cached charts under `bytes` in total
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Directory charts are stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, url: &str, extension: &str) -> PathBuf {
+        let hash = hex::encode(Sha256::digest(url.as_bytes()));
+        self.dir.join(format!("{}.{}", hash, extension))
+    }
+
+    fn is_expired(&self, modified: SystemTime) -> bool {
+        self.ttl
+            .is_some_and(|ttl| modified.elapsed().is_ok_and(|age| age >= ttl))
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = self.dir.join(format!(
+            ".{}.{}-{}.part",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp, bytes)
+            .and_then(|()| fs::rename(&temp, path))
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&temp);
+            })
+    }
+
+    /// Delete expired charts, then the oldest ones until the cache fits its maximum size
+    ///
+    /// Files removed meanwhile, such as by another process sharing the directory, are
+    /// skipped rather than ending the pass.
+    fn evict(&self) -> io::Result<()> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let Some(entry) = unless_vanished(entry)? else {
+                continue;
+            };
+            let Some(metadata) = unless_vanished(entry.metadata())? else {
+                continue;
+            };
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if metadata.is_file() && !hidden {
+                files.push((metadata.modified()?, metadata.len(), entry.path()));
+            }
+        }
+        files.sort();
+
+        let mut size: u64 = files.iter().map(|(_, len, _)| len).sum();
+        for (modified, len, path) in files {
+            let oversized = self.max_size.is_some_and(|max| size > max);
+            if !oversized && !self.is_expired(modified) {
+                continue;
+            }
+            unless_vanished(fs::remove_file(path))?;
+            size -= len;
+        }
+        Ok(())
+    }
+}
+
+impl ChartCache for FileCache {
+    fn get(&self, url: &str) -> Option<CachedChart> {
+        let formats = [OutputFormat::Png, OutputFormat::Svg, OutputFormat::Gif]
+            .map(Some)
+            .into_iter()
+            .chain([None]);
+        for format in formats {
+            let path = self.path(url, format.map_or(UNKNOWN_EXTENSION, |f| f.extension()));
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            if metadata.modified().is_ok_and(|m| self.is_expired(m)) {
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+            return fs::read(&path)
+                .ok()
+                .map(|bytes| CachedChart { bytes, format });
+        }
+        None
+    }
+
+    fn put(&self, url: &str, chart: CachedChart) {
+        let extension = chart.format.map_or(UNKNOWN_EXTENSION, |f| f.extension());
+        if self.write(&self.path(url, extension), &chart.bytes).is_ok() {
+            let _ = self.evict();
+        }
+    }
+}
+
+/// `None` when the file was removed before the operation could run
+fn unless_vanished<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn chart(bytes: &str) -> CachedChart {
+        CachedChart {
+            bytes: bytes.as_bytes().to_vec(),
+            format: Some(OutputFormat::Png),
+        }
+    }
+
+    #[test]
+    fn test_memory_cache_evicts_least_recently_used() {
+        let cache = MemoryCache::new(2);
+        cache.put("a", chart("a"));
+        cache.put("b", chart("b"));
+        assert_eq!(cache.get("a"), Some(chart("a")));
+        cache.put("c", chart("c"));
+
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(chart("a")));
+        assert_eq!(cache.get("c"), Some(chart("c")));
+        assert_eq!(cache.len(), 2);
+
+        cache.put("c", chart("c2"));
+        assert_eq!(cache.get("c"), Some(chart("c2")));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_memory_cache_ttl() {
+        let cache = MemoryCache::new(2).ttl(Duration::ZERO);
+        cache.put("a", chart("a"));
+        assert_eq!(cache.get("a"), None);
+        assert!(cache.is_empty());
+
+        let disabled = MemoryCache::new(0);
+        disabled.put("a", chart("a"));
+        assert_eq!(disabled.get("a"), None);
+    }
+
+    #[test]
+    fn test_file_cache_round_trip() {
+        let dir = TempDir::new("cache-round-trip");
+        let cache = FileCache::new(dir.path()).unwrap();
+        let svg = CachedChart {
+            bytes: b"<svg/>".to_vec(),
+            format: Some(OutputFormat::Svg),
+        };
+        cache.put("https://image-charts.com/chart?cht=qr", svg.clone());
+        cache.put("https://image-charts.com/chart?cht=p", chart("png"));
+
+        assert_eq!(
+            cache.get("https://image-charts.com/chart?cht=qr"),
+            Some(svg)
+        );
+        assert_eq!(
+            cache.get("https://image-charts.com/chart?cht=p"),
+            Some(chart("png"))
+        );
+        assert_eq!(cache.get("https://image-charts.com/chart?cht=bvs"), None);
+
+        let stored = cache.path("https://image-charts.com/chart?cht=qr", "svg");
+        assert!(stored.exists());
+        assert_eq!(stored.file_stem().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_file_cache_ttl_and_max_size() {
+        let dir = TempDir::new("cache-ttl");
+        let expiring = FileCache::new(dir.path()).unwrap().ttl(Duration::ZERO);
+        expiring.put("a", chart("a"));
+        assert_eq!(expiring.get("a"), None);
+        assert_eq!(fs::read_dir(expiring.dir()).unwrap().count(), 0);
+
+        let dir = TempDir::new("cache-max-size");
+        let bounded = FileCache::new(dir.path()).unwrap().max_size(10);
+        bounded.put("a", chart("aaaa"));
+        std::thread::sleep(Duration::from_millis(20));
+        bounded.put("b", chart("bbbb"));
+        std::thread::sleep(Duration::from_millis(20));
+        bounded.put("c", chart("cccc"));
+        assert_eq!(bounded.get("a"), None);
+        assert_eq!(bounded.get("b"), Some(chart("bbbb")));
+        assert_eq!(bounded.get("c"), Some(chart("cccc")));
+    }
+
+    #[test]
+    fn test_file_cache_eviction_skips_vanished_files() {
+        let dir = TempDir::new("cache-vanished");
+        let bounded = FileCache::new(dir.path()).unwrap().max_size(10);
+        bounded.put("a", chart("aaaa"));
+        std::thread::sleep(Duration::from_millis(20));
+        bounded.put("b", chart("bbbb"));
+        fs::remove_file(bounded.path("a", "png")).unwrap();
+        assert!(bounded.evict().is_ok());
+
+        std::thread::sleep(Duration::from_millis(20));
+        bounded.put("c", chart("cccc"));
+        std::thread::sleep(Duration::from_millis(20));
+        bounded.put("d", chart("dddd"));
+        assert_eq!(bounded.get("b"), None);
+        assert_eq!(bounded.get("c"), Some(chart("cccc")));
+        assert_eq!(bounded.get("d"), Some(chart("dddd")));
+        assert_eq!(dir.files().len(), 2);
+    }
+}
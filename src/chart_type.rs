@@ -0,0 +1,273 @@
+//! Typed chart type (`cht`)
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{ImageChartsError, ImageChartsErrorKind};
+
+/// GraphViz layout engine, appended to `gv` as `gv:<engine>`
+///
+/// [Reference documentation](https://documentation.image-charts.com/graph-viz-charts/)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GraphVizEngine {
+    /// `dot`: hierarchical layouts of directed graphs
+    Dot,
+    /// `neato`: spring model layouts
+    Neato,
+    /// `circo`: circular layouts
+    Circo,
+    /// `fdp`: force-directed spring model layouts
+    Fdp,
+    /// `osage`: array-based layouts of clustered graphs
+    Osage,
+    /// `twopi`: radial layouts
+    Twopi,
+}
+
+impl GraphVizEngine {
+    /// Every layout engine
+    pub const ALL: [GraphVizEngine; 6] = [
+        GraphVizEngine::Dot,
+        GraphVizEngine::Neato,
+        GraphVizEngine::Circo,
+        GraphVizEngine::Fdp,
+        GraphVizEngine::Osage,
+        GraphVizEngine::Twopi,
+    ];
+
+    /// Engine name as used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GraphVizEngine::Dot => "dot",
+            GraphVizEngine::Neato => "neato",
+            GraphVizEngine::Circo => "circo",
+            GraphVizEngine::Fdp => "fdp",
+            GraphVizEngine::Osage => "osage",
+            GraphVizEngine::Twopi => "twopi",
+        }
+    }
+}
+
+/// Chart type, serialized into the `cht` parameter
+///
+/// [Reference documentation](https://documentation.image-charts.com/reference/chart-type/)
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ ChartType, ImageCharts };
+///
+/// let url = ImageCharts::new().cht(ChartType::BarVerticalStacked).to_url();
+/// assert!(url.contains("cht=bvs"));
+///
+/// let parsed: ChartType = "lc:nda".parse().unwrap();
+/// assert_eq!(parsed, ChartType::LineNoAxes);
+/// assert!("bvgs".parse::<ChartType>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChartType {
+    /// `bvs`: vertical stacked bar chart
+    BarVerticalStacked,
+    /// `bvg`: vertical grouped bar chart
+    BarVerticalGrouped,
+    /// `bhs`: horizontal stacked bar chart
+    BarHorizontalStacked,
+    /// `bhg`: horizontal grouped bar chart
+    BarHorizontalGrouped,
+    /// `bvo`: vertical overlapped bar chart
+    BarVerticalOverlapped,
+    /// `p`: pie chart
+    Pie,
+    /// `p3`: three-dimensional pie chart (rendered in 2D)
+    Pie3d,
+    /// `pc`: concentric pie chart
+    PieConcentric,
+    /// `pd`: doughnut chart
+    Doughnut,
+    /// `ls`: sparkline
+    Sparkline,
+    /// `lc`: line chart
+    Line,
+    /// `lxy`: line chart with x and y coordinates
+    LineXY,
+    /// `ls:nda`: sparkline without default axes
+    SparklineNoAxes,
+    /// `lc:nda`: line chart without default axes
+    LineNoAxes,
+    /// `lxy:nda`: xy line chart without default axes
+    LineXYNoAxes,
+    /// `pa`: polar area chart
+    PolarArea,
+    /// `bb`: bubble chart
+    Bubble,
+    /// `gv` or `gv:<engine>`: GraphViz chart
+    GraphViz(Option<GraphVizEngine>),
+    /// `qr`: QR code
+    QrCode,
+    /// `r`: radar chart
+    Radar,
+}
+
+impl ChartType {
+    /// Every chart type accepted by the API
+    pub fn all() -> Vec<ChartType> {
+        let mut all = vec![
+            ChartType::BarVerticalStacked,
+            ChartType::BarVerticalGrouped,
+            ChartType::BarHorizontalStacked,
+            ChartType::BarHorizontalGrouped,
+            ChartType::BarVerticalOverlapped,
+            ChartType::Pie,
+            ChartType::Pie3d,
+            ChartType::PieConcentric,
+            ChartType::Doughnut,
+            ChartType::Sparkline,
+            ChartType::Line,
+            ChartType::LineXY,
+            ChartType::SparklineNoAxes,
+            ChartType::LineNoAxes,
+            ChartType::LineXYNoAxes,
+            ChartType::PolarArea,
+            ChartType::Bubble,
+            ChartType::GraphViz(None),
+        ];
+        all.extend(
+            GraphVizEngine::ALL
+                .iter()
+                .map(|engine| ChartType::GraphViz(Some(*engine))),
+        );
+        all.extend([ChartType::QrCode, ChartType::Radar]);
+        all
+    }
+
+    /// Chart type as used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChartType::BarVerticalStacked => "bvs",
+            ChartType::BarVerticalGrouped => "bvg",
+            ChartType::BarHorizontalStacked => "bhs",
+            ChartType::BarHorizontalGrouped => "bhg",
+            ChartType::BarVerticalOverlapped => "bvo",
+            ChartType::Pie => "p",
+            ChartType::Pie3d => "p3",
+            ChartType::PieConcentric => "pc",
+            ChartType::Doughnut => "pd",
+            ChartType::Sparkline => "ls",
+            ChartType::Line => "lc",
+            ChartType::LineXY => "lxy",
+            ChartType::SparklineNoAxes => "ls:nda",
+            ChartType::LineNoAxes => "lc:nda",
+            ChartType::LineXYNoAxes => "lxy:nda",
+            ChartType::PolarArea => "pa",
+            ChartType::Bubble => "bb",
+            ChartType::GraphViz(None) => "gv",
+            ChartType::GraphViz(Some(GraphVizEngine::Dot)) => "gv:dot",
+            ChartType::GraphViz(Some(GraphVizEngine::Neato)) => "gv:neato",
+            ChartType::GraphViz(Some(GraphVizEngine::Circo)) => "gv:circo",
+            ChartType::GraphViz(Some(GraphVizEngine::Fdp)) => "gv:fdp",
+            ChartType::GraphViz(Some(GraphVizEngine::Osage)) => "gv:osage",
+            ChartType::GraphViz(Some(GraphVizEngine::Twopi)) => "gv:twopi",
+            ChartType::QrCode => "qr",
+            ChartType::Radar => "r",
+        }
+    }
+
+    /// Bar charts (`bvs`, `bvg`, `bhs`, `bhg`, `bvo`)
+    pub fn is_bar(&self) -> bool {
+        matches!(
+            self,
+            ChartType::BarVerticalStacked
+                | ChartType::BarVerticalGrouped
+                | ChartType::BarHorizontalStacked
+                | ChartType::BarHorizontalGrouped
+                | ChartType::BarVerticalOverlapped
+        )
+    }
+
+    /// Pie and doughnut charts (`p`, `p3`, `pc`, `pd`)
+    pub fn is_pie(&self) -> bool {
+        matches!(
+            self,
+            ChartType::Pie | ChartType::Pie3d | ChartType::PieConcentric | ChartType::Doughnut
+        )
+    }
+
+    /// Line charts and sparklines, with or without default axes
+    pub fn is_line(&self) -> bool {
+        matches!(
+            self,
+            ChartType::Sparkline
+                | ChartType::Line
+                | ChartType::LineXY
+                | ChartType::SparklineNoAxes
+                | ChartType::LineNoAxes
+                | ChartType::LineXYNoAxes
+        )
+    }
+
+    /// GraphViz charts, whatever the layout engine
+    pub fn is_graph_viz(&self) -> bool {
+        matches!(self, ChartType::GraphViz(_))
+    }
+}
+
+impl fmt::Display for ChartType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ChartType {
+    type Err = ImageChartsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ChartType::all()
+            .into_iter()
+            .find(|chart_type| chart_type.as_str() == s)
+            .ok_or_else(|| {
+                ImageChartsError::new(
+                    ImageChartsErrorKind::InvalidConfig,
+                    format!("Unknown chart type \"{}\"", s),
+                )
+                .with_code("INVALID_CHART_TYPE")
+            })
+    }
+}
+
+impl From<ChartType> for String {
+    fn from(chart_type: ChartType) -> Self {
+        chart_type.as_str().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_all_chart_types() {
+        for chart_type in ChartType::all() {
+            assert_eq!(
+                chart_type.to_string().parse::<ChartType>().unwrap(),
+                chart_type
+            );
+        }
+        assert_eq!(ChartType::all().len(), 26);
+    }
+
+    #[test]
+    fn test_parse_unknown_chart_type() {
+        let err = "bvgs".parse::<ChartType>().unwrap_err();
+        assert_eq!(err.code.as_deref(), Some("INVALID_CHART_TYPE"));
+        assert!(err.message.contains("bvgs"));
+    }
+
+    #[test]
+    fn test_chart_type_families() {
+        assert!(ChartType::BarVerticalOverlapped.is_bar());
+        assert!(ChartType::Doughnut.is_pie());
+        assert!(ChartType::LineXYNoAxes.is_line());
+        assert!(ChartType::GraphViz(Some(GraphVizEngine::Fdp)).is_graph_viz());
+        assert!(!ChartType::QrCode.is_line());
+    }
+}
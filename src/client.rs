@@ -0,0 +1,1019 @@
+//! Long-lived HTTP clients sharing a connection pool across chart downloads
+
+#[cfg(feature = "async")]
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+#[cfg(feature = "async")]
+use tokio::sync::watch;
+
+use crate::retry::{parse_retry_after, Failure};
+use crate::transport::{io_error, TransportRequest, TransportResponse};
+#[cfg(feature = "blocking")]
+use crate::transport::{BlockingReqwestTransport, BlockingTransport};
+#[cfg(feature = "async")]
+use crate::transport::{ReqwestTransport, Transport};
+use crate::{CachedChart, ImageCharts, ImageChartsConfig, ImageChartsError, OutputFormat};
+
+fn response_failure(status: u16, headers: &HeaderMap) -> Failure {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let validation = header("x-ic-error-validation");
+    Failure::Response {
+        error: ImageCharts::parse_error_response(
+            status,
+            header("x-ic-error-code"),
+            validation.as_deref(),
+        ),
+        validation: validation.is_some(),
+        retry_after: parse_retry_after(header("retry-after").as_deref()),
+    }
+}
+
+fn content_format(headers: &HeaderMap) -> Option<OutputFormat> {
+    headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(OutputFormat::from_mime_type)
+}
+
+/// Image format announced by a success response
+fn check_response(response: &TransportResponse) -> Result<Option<OutputFormat>, Failure> {
+    if (200..300).contains(&response.status) {
+        Ok(content_format(&response.headers))
+    } else {
+        Err(response_failure(response.status, &response.headers))
+    }
+}
+
+/// `path` with `extension` appended when it has none
+fn file_path(path: &Path, extension: &str) -> PathBuf {
+    if path.extension().is_some() {
+        path.to_path_buf()
+    } else {
+        path.with_extension(extension)
+    }
+}
+
+/// Hidden sibling of `path` an image is streamed to before being renamed to `path`
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    path.with_file_name(format!(
+        ".{}.{}-{}.part",
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+impl ImageCharts {
+    /// Same chart parameters, rendered with another configuration
+    fn with_client_config(&self, config: &ImageChartsConfig) -> ImageCharts {
+        ImageCharts {
+            config: config.clone(),
+            query: self.query.clone(),
+        }
+    }
+
+    /// Request rendering this chart with `config`, validated first when enabled
+    fn request(&self, config: &ImageChartsConfig) -> Result<ChartRequest, ImageChartsError> {
+        let chart = self.with_client_config(config);
+        if config.validate {
+            chart.check()?;
+        }
+        Ok(ChartRequest {
+            request: TransportRequest::new(chart.to_url(), &chart.build_user_agent())?,
+            account: chart.query.get("icac").cloned(),
+        })
+    }
+}
+
+/// Request sent on each attempt, with the account it is rate limited under
+struct ChartRequest {
+    request: TransportRequest,
+    account: Option<String>,
+}
+
+impl ChartRequest {
+    /// How long to wait for the rate limiter of `config`, if any
+    fn rate_limit_delay(&self, config: &ImageChartsConfig) -> Option<Duration> {
+        config
+            .rate_limiter
+            .as_ref()
+            .map(|limiter| limiter.reserve(self.account.as_deref()))
+            .filter(|delay| !delay.is_zero())
+    }
+
+    /// Let the rate limiter of `config` adapt to `response`
+    fn record_response(&self, config: &ImageChartsConfig, response: &TransportResponse) {
+        if let (Some(limiter), 429) = (&config.rate_limiter, response.status) {
+            limiter.throttled(self.account.as_deref(), &response.headers);
+        }
+    }
+
+    /// Chart previously stored in the cache of `config`, if any
+    fn cached(&self, config: &ImageChartsConfig) -> Option<CachedChart> {
+        config.cache.as_ref()?.get(&self.request.url)
+    }
+
+    /// Store a downloaded chart in the cache of `config`, if any
+    fn store(&self, config: &ImageChartsConfig, bytes: &[u8], format: Option<OutputFormat>) {
+        if let Some(cache) = &config.cache {
+            let chart = CachedChart {
+                bytes: bytes.to_vec(),
+                format,
+            };
+            cache.put(&self.request.url, chart);
+        }
+    }
+}
+
+/// Success response serving a cached chart
+fn cached_response(chart: CachedChart) -> (TransportResponse, Option<OutputFormat>) {
+    let response = TransportResponse {
+        status: 200,
+        headers: HeaderMap::new(),
+        body: chart.bytes.into(),
+    };
+    (response, chart.format)
+}
+
+/// Image bytes and announced format of a buffered download
+#[cfg(feature = "async")]
+type Fetched = Result<(Vec<u8>, Option<OutputFormat>), ImageChartsError>;
+
+/// Buffered downloads in progress by URL, awaited by identical concurrent calls
+#[cfg(feature = "async")]
+type InFlight = Mutex<HashMap<String, watch::Receiver<Option<Fetched>>>>;
+
+/// Downloads in progress of the clients built by the async `ImageCharts` methods
+#[cfg(feature = "async")]
+static SHARED_IN_FLIGHT: LazyLock<Arc<InFlight>> = LazyLock::new(Arc::default);
+
+/// Forgets a download in progress once it completes or is cancelled
+#[cfg(feature = "async")]
+struct InFlightGuard<'a> {
+    in_flight: &'a InFlight,
+    url: &'a str,
+}
+
+#[cfg(feature = "async")]
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(self.url);
+    }
+}
+
+/// Async client reusing its connection pool for every chart it renders
+///
+/// The client owns the [`ImageChartsConfig`]: host, secret, timeout and user-agent of
+/// the client are used whatever the configuration of the rendered charts. Cloning the
+/// client is cheap and shares the pool.
+///
+/// Concurrent `to_buffer` and `to_data_uri` calls for the same chart URL on a client or
+/// its clones are coalesced: a single request is sent and every caller gets its bytes, or
+/// a clone of its error. [`ImageCharts::to_buffer`] and [`ImageCharts::to_data_uri`]
+/// build a client per call but coalesce their downloads across the whole process.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use image_charts::{ ImageCharts, ImageChartsClient };
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let client = ImageChartsClient::new()?;
+///
+///     for size in ["100x100", "200x200", "300x300"] {
+///         let chart = ImageCharts::new().cht("p").chd("t:60,40").chs(size);
+///         let buffer = client.to_buffer(&chart).await?;
+///         println!("{}: {} bytes", size, buffer.len());
+///     }
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[derive(Clone)]
+pub struct ImageChartsClient {
+    config: ImageChartsConfig,
+    transport: Arc<dyn Transport>,
+    in_flight: Arc<InFlight>,
+}
+
+#[cfg(feature = "async")]
+impl fmt::Debug for ImageChartsClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageChartsClient")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "async")]
+impl ImageChartsClient {
+    /// Create a client with the default configuration
+    pub fn new() -> Result<Self, ImageChartsError> {
+        Self::with_config(ImageChartsConfig::default())
+    }
+
+    /// Create a client with a custom configuration
+    pub fn with_config(config: ImageChartsConfig) -> Result<Self, ImageChartsError> {
+        let transport = ReqwestTransport::new(&config)?;
+        Ok(Self::with_transport(config, transport))
+    }
+
+    /// Create a client sending its requests through a custom [`Transport`]
+    ///
+    /// The timeout of `config` is left to the transport.
+    pub fn with_transport(config: ImageChartsConfig, transport: impl Transport + 'static) -> Self {
+        Self {
+            config,
+            transport: Arc::new(transport),
+            in_flight: Arc::default(),
+        }
+    }
+
+    /// Client of the `ImageCharts` methods, coalescing downloads with every other one
+    pub(crate) fn shared(config: ImageChartsConfig) -> Result<Self, ImageChartsError> {
+        Self::with_config(config).map(Self::sharing_in_flight)
+    }
+
+    /// Coalesce downloads with the clients of the `ImageCharts` methods
+    fn sharing_in_flight(self) -> Self {
+        Self {
+            in_flight: SHARED_IN_FLIGHT.clone(),
+            ..self
+        }
+    }
+
+    /// Configuration used for every chart rendered by this client
+    pub fn config(&self) -> &ImageChartsConfig {
+        &self.config
+    }
+
+    /// Start a new chart using the configuration of this client
+    pub fn chart(&self) -> ImageCharts {
+        ImageCharts::with_config(self.config.clone())
+    }
+
+    /// Render a chart and return the image as bytes
+    ///
+    /// Transient failures are retried according to [`ImageChartsConfig::retry`].
+    pub async fn to_buffer(&self, chart: &ImageCharts) -> Result<Vec<u8>, ImageChartsError> {
+        self.fetch(chart).await.map(|(buffer, _)| buffer)
+    }
+
+    /// Image bytes along with the format announced by the response `Content-Type`
+    async fn fetch(
+        &self,
+        chart: &ImageCharts,
+    ) -> Result<(Vec<u8>, Option<OutputFormat>), ImageChartsError> {
+        let request = chart.request(&self.config)?;
+        if let Some(cached) = request.cached(&self.config) {
+            return Ok((cached.bytes, cached.format));
+        }
+        self.coalesced(&request).await
+    }
+
+    /// Download `request`, or wait for the identical download already in progress
+    async fn coalesced(&self, request: &ChartRequest) -> Fetched {
+        let url = request.request.url.as_str();
+        let sender = loop {
+            let mut pending = {
+                let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+                match in_flight.get(url) {
+                    Some(pending) => pending.clone(),
+                    None => {
+                        let (sender, receiver) = watch::channel(None);
+                        in_flight.insert(url.to_string(), receiver);
+                        break sender;
+                    }
+                }
+            };
+            // A cancelled download leaves its waiters to start another one
+            let fetched = pending.wait_for(Option::is_some).await.ok();
+            if let Some(fetched) = fetched.and_then(|fetched| fetched.clone()) {
+                return fetched;
+            }
+        };
+        let _guard = InFlightGuard {
+            in_flight: &self.in_flight,
+            url,
+        };
+        let fetched = self.download(request).await;
+        sender.send_replace(Some(fetched.clone()));
+        fetched
+    }
+
+    /// Download `request` through the transport and store it in the cache
+    async fn download(&self, request: &ChartRequest) -> Fetched {
+        let (buffer, format) = self.retry(|| self.attempt(request)).await?;
+        request.store(&self.config, &buffer, format);
+        Ok((buffer, format))
+    }
+
+    /// Success response with its body left unread, along with its announced format
+    async fn open(
+        &self,
+        chart: &ImageCharts,
+    ) -> Result<(TransportResponse, Option<OutputFormat>), ImageChartsError> {
+        let request = chart.request(&self.config)?;
+        if let Some(cached) = request.cached(&self.config) {
+            return Ok(cached_response(cached));
+        }
+        self.retry(|| self.send(&request)).await
+    }
+
+    /// Run `attempt` until it succeeds or the retry policy gives up
+    async fn retry<T, F, Fut>(&self, mut attempt: F) -> Result<T, ImageChartsError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Failure>>,
+    {
+        let mut attempts = 1;
+        loop {
+            let failure = match attempt().await {
+                Ok(value) => return Ok(value),
+                Err(failure) => failure,
+            };
+            match self.config.retry.delay(attempts, &failure) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(failure.into_error()),
+            }
+            attempts += 1;
+        }
+    }
+
+    async fn send(
+        &self,
+        request: &ChartRequest,
+    ) -> Result<(TransportResponse, Option<OutputFormat>), Failure> {
+        if let Some(delay) = request.rate_limit_delay(&self.config) {
+            tokio::time::sleep(delay).await;
+        }
+        let response = self
+            .transport
+            .send(request.request.clone())
+            .await
+            .map_err(Failure::Transport)?;
+        request.record_response(&self.config, &response);
+        let format = check_response(&response)?;
+        Ok((response, format))
+    }
+
+    async fn attempt(
+        &self,
+        request: &ChartRequest,
+    ) -> Result<(Vec<u8>, Option<OutputFormat>), Failure> {
+        let (response, format) = self.send(request).await?;
+        let status = response.status;
+        response
+            .body
+            .bytes()
+            .await
+            .map(|buffer| (buffer, format))
+            .map_err(|e| Failure::Transport(e.with_status(status)))
+    }
+
+    /// Render a chart and stream the image to `writer`, flushed once complete
+    ///
+    /// The body is written chunk by chunk as it is received. Failures are retried until
+    /// the response starts, never once part of the image was written. Returns the image
+    /// format, announced by the response `Content-Type` or implied by `chof` and `chan`.
+    pub async fn to_async_writer<W>(
+        &self,
+        chart: &ImageCharts,
+        writer: &mut W,
+    ) -> Result<OutputFormat, ImageChartsError>
+    where
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        let (response, format) = self.open(chart).await?;
+        response.body.write_to(writer).await?;
+        Ok(format.unwrap_or_else(|| chart.inferred_output_format()))
+    }
+
+    /// Render a chart and write the image to a file
+    ///
+    /// When `path` has no extension, the one matching the image format is appended. The
+    /// image is streamed to a temporary file in the same directory, renamed to `path`
+    /// once complete: a failed download never leaves a truncated chart behind.
+    pub async fn to_file(
+        &self,
+        chart: &ImageCharts,
+        path: impl AsRef<Path>,
+    ) -> Result<(), ImageChartsError> {
+        let (response, format) = self.open(chart).await?;
+        let extension = format.map_or_else(|| chart.get_file_format(), |f| f.extension());
+        let path = file_path(path.as_ref(), extension);
+        let temp = temp_path(&path);
+
+        let result = async {
+            let mut file = tokio::fs::File::create(&temp).await.map_err(io_error)?;
+            response.body.write_to(&mut file).await?;
+            file.sync_all().await.map_err(io_error)?;
+            drop(file);
+            tokio::fs::rename(&temp, &path).await.map_err(io_error)
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&temp).await;
+        }
+        result
+    }
+
+    /// Render a chart and return it as a data URI
+    ///
+    /// The MIME type comes from the response `Content-Type`, falling back to the one
+    /// implied by `chof` and `chan`. SVG images are URL-encoded, others base64-encoded.
+    pub async fn to_data_uri(&self, chart: &ImageCharts) -> Result<String, ImageChartsError> {
+        let (buffer, format) = self.fetch(chart).await?;
+        let format = format.unwrap_or_else(|| chart.inferred_output_format());
+        Ok(format.data_uri(&buffer))
+    }
+}
+
+/// Blocking client reusing its connection pool for every chart it renders
+///
+/// Blocking counterpart of [`ImageChartsClient`]. Like any `reqwest` blocking client it
+/// must not be created or dropped from within an async runtime.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use image_charts::{ BlockingImageChartsClient, ImageCharts };
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let client = BlockingImageChartsClient::new()?;
+///     let chart = ImageCharts::new().cht("p").chd("t:60,40").chs("100x100");
+///     let buffer = client.to_buffer(&chart)?;
+///     println!("Image size: {} bytes", buffer.len());
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "blocking")]
+#[derive(Clone)]
+pub struct BlockingImageChartsClient {
+    config: ImageChartsConfig,
+    transport: Arc<dyn BlockingTransport>,
+}
+
+#[cfg(feature = "blocking")]
+impl fmt::Debug for BlockingImageChartsClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingImageChartsClient")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl BlockingImageChartsClient {
+    /// Create a client with the default configuration
+    pub fn new() -> Result<Self, ImageChartsError> {
+        Self::with_config(ImageChartsConfig::default())
+    }
+
+    /// Create a client with a custom configuration
+    pub fn with_config(config: ImageChartsConfig) -> Result<Self, ImageChartsError> {
+        let transport = BlockingReqwestTransport::new(&config)?;
+        Ok(Self::with_transport(config, transport))
+    }
+
+    /// Create a client sending its requests through a custom [`BlockingTransport`]
+    ///
+    /// The timeout of `config` is left to the transport.
+    pub fn with_transport(
+        config: ImageChartsConfig,
+        transport: impl BlockingTransport + 'static,
+    ) -> Self {
+        Self {
+            config,
+            transport: Arc::new(transport),
+        }
+    }
+
+    /// Configuration used for every chart rendered by this client
+    pub fn config(&self) -> &ImageChartsConfig {
+        &self.config
+    }
+
+    /// Start a new chart using the configuration of this client
+    pub fn chart(&self) -> ImageCharts {
+        ImageCharts::with_config(self.config.clone())
+    }
+
+    /// Render a chart and return the image as bytes
+    ///
+    /// Transient failures are retried according to [`ImageChartsConfig::retry`].
+    pub fn to_buffer(&self, chart: &ImageCharts) -> Result<Vec<u8>, ImageChartsError> {
+        self.fetch(chart).map(|(buffer, _)| buffer)
+    }
+
+    /// Image bytes along with the format announced by the response `Content-Type`
+    fn fetch(
+        &self,
+        chart: &ImageCharts,
+    ) -> Result<(Vec<u8>, Option<OutputFormat>), ImageChartsError> {
+        let request = chart.request(&self.config)?;
+        if let Some(cached) = request.cached(&self.config) {
+            return Ok((cached.bytes, cached.format));
+        }
+        let (buffer, format) = self.retry(|| self.attempt(&request))?;
+        request.store(&self.config, &buffer, format);
+        Ok((buffer, format))
+    }
+
+    /// Success response with its body left unread, along with its announced format
+    fn open(
+        &self,
+        chart: &ImageCharts,
+    ) -> Result<(TransportResponse, Option<OutputFormat>), ImageChartsError> {
+        let request = chart.request(&self.config)?;
+        if let Some(cached) = request.cached(&self.config) {
+            return Ok(cached_response(cached));
+        }
+        self.retry(|| self.send(&request))
+    }
+
+    /// Run `attempt` until it succeeds or the retry policy gives up
+    fn retry<T>(
+        &self,
+        mut attempt: impl FnMut() -> Result<T, Failure>,
+    ) -> Result<T, ImageChartsError> {
+        let mut attempts = 1;
+        loop {
+            let failure = match attempt() {
+                Ok(value) => return Ok(value),
+                Err(failure) => failure,
+            };
+            match self.config.retry.delay(attempts, &failure) {
+                Some(delay) => std::thread::sleep(delay),
+                None => return Err(failure.into_error()),
+            }
+            attempts += 1;
+        }
+    }
+
+    fn send(
+        &self,
+        request: &ChartRequest,
+    ) -> Result<(TransportResponse, Option<OutputFormat>), Failure> {
+        if let Some(delay) = request.rate_limit_delay(&self.config) {
+            std::thread::sleep(delay);
+        }
+        let response = self
+            .transport
+            .send(request.request.clone())
+            .map_err(Failure::Transport)?;
+        request.record_response(&self.config, &response);
+        let format = check_response(&response)?;
+        Ok((response, format))
+    }
+
+    fn attempt(&self, request: &ChartRequest) -> Result<(Vec<u8>, Option<OutputFormat>), Failure> {
+        let (response, format) = self.send(request)?;
+        let status = response.status;
+        response
+            .body
+            .bytes_blocking()
+            .map(|buffer| (buffer, format))
+            .map_err(|e| Failure::Transport(e.with_status(status)))
+    }
+
+    /// Render a chart and stream the image to `writer`, flushed once complete
+    ///
+    /// The body is written chunk by chunk as it is received. Failures are retried until
+    /// the response starts, never once part of the image was written. Returns the image
+    /// format, announced by the response `Content-Type` or implied by `chof` and `chan`.
+    pub fn to_writer<W>(
+        &self,
+        chart: &ImageCharts,
+        writer: &mut W,
+    ) -> Result<OutputFormat, ImageChartsError>
+    where
+        W: std::io::Write + ?Sized,
+    {
+        let (response, format) = self.open(chart)?;
+        response.body.write_to_blocking(writer)?;
+        Ok(format.unwrap_or_else(|| chart.inferred_output_format()))
+    }
+
+    /// Render a chart and write the image to a file
+    ///
+    /// When `path` has no extension, the one matching the image format is appended. The
+    /// image is streamed to a temporary file in the same directory, renamed to `path`
+    /// once complete: a failed download never leaves a truncated chart behind.
+    pub fn to_file(
+        &self,
+        chart: &ImageCharts,
+        path: impl AsRef<Path>,
+    ) -> Result<(), ImageChartsError> {
+        let (response, format) = self.open(chart)?;
+        let extension = format.map_or_else(|| chart.get_file_format(), |f| f.extension());
+        let path = file_path(path.as_ref(), extension);
+        let temp = temp_path(&path);
+
+        let result = std::fs::File::create(&temp)
+            .map_err(io_error)
+            .and_then(|mut file| {
+                response.body.write_to_blocking(&mut file)?;
+                file.sync_all().map_err(io_error)
+            })
+            .and_then(|()| std::fs::rename(&temp, &path).map_err(io_error));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        result
+    }
+
+    /// Render a chart and return it as a data URI
+    ///
+    /// The MIME type comes from the response `Content-Type`, falling back to the one
+    /// implied by `chof` and `chan`. SVG images are URL-encoded, others base64-encoded.
+    pub fn to_data_uri(&self, chart: &ImageCharts) -> Result<String, ImageChartsError> {
+        let (buffer, format) = self.fetch(chart)?;
+        let format = format.unwrap_or_else(|| chart.inferred_output_format());
+        Ok(format.data_uri(&buffer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockResponse, MockTransport};
+    use crate::temp_dir::TempDir;
+    use crate::RetryPolicy;
+
+    fn validating_config() -> ImageChartsConfig {
+        ImageChartsConfig {
+            host: "unreachable.invalid".to_string(),
+            validate: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_with_client_config_keeps_query() {
+        let config = ImageChartsConfig {
+            host: "charts.internal".to_string(),
+            ..Default::default()
+        };
+        let chart = ImageCharts::new().cht("p").chs("100x100");
+        let url = chart.with_client_config(&config).to_url();
+        assert_eq!(url, "https://charts.internal/chart?chs=100x100&cht=p");
+    }
+
+    #[test]
+    fn test_temp_path_is_hidden_sibling() {
+        let first = temp_path(Path::new("out/chart.png"));
+        assert_eq!(first.parent(), Some(Path::new("out")));
+        let name = first.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with(".chart.png.") && name.ends_with(".part"));
+        assert_ne!(temp_path(Path::new("out/chart.png")), first);
+    }
+
+    #[test]
+    fn test_file_path_extension() {
+        assert_eq!(
+            file_path(Path::new("out/chart"), "svg"),
+            PathBuf::from("out/chart.svg")
+        );
+        assert_eq!(
+            file_path(Path::new("chart.png"), "gif"),
+            PathBuf::from("chart.png")
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_client_uses_its_own_config() {
+        let client = ImageChartsClient::with_config(validating_config()).unwrap();
+        assert_eq!(client.chart().config.host, "unreachable.invalid");
+
+        let err = client
+            .to_buffer(&ImageCharts::new().cht("p"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code, Some(400));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_client_uses_its_own_config() {
+        let client = BlockingImageChartsClient::with_config(validating_config()).unwrap();
+        assert_eq!(client.chart().config.host, "unreachable.invalid");
+
+        let err = client.to_buffer(&ImageCharts::new().cht("p")).unwrap_err();
+        assert_eq!(err.status_code, Some(400));
+    }
+
+    fn signed_config() -> ImageChartsConfig {
+        ImageChartsConfig {
+            secret: Some("plop".to_string()),
+            retry: RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: std::time::Duration::ZERO,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn signed_chart() -> ImageCharts {
+        ImageCharts::new()
+            .cht("p")
+            .chs("100x100")
+            .icac("test_fixture")
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_client_sends_signed_request_through_transport() {
+        let mock = MockTransport::new();
+        mock.push(MockResponse::image(OutputFormat::Svg, "<svg/>"));
+        let client = ImageChartsClient::with_transport(signed_config(), mock.clone());
+
+        let data_uri = client.to_data_uri(&signed_chart()).await.unwrap();
+        assert_eq!(data_uri, "data:image/svg+xml,%3Csvg/%3E");
+
+        let request = &mock.requests()[0];
+        assert!(client.chart().verify_signature(&request.url).is_ok());
+        assert_eq!(
+            request.headers[reqwest::header::USER_AGENT],
+            format!(
+                "rust-image_charts/{} (test_fixture)",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_client_parses_errors_from_transport() {
+        let mock = MockTransport::new();
+        mock.push(MockResponse::error(503, "IC_UNAVAILABLE"));
+        mock.push(MockResponse::validation(&[
+            "\"chs\" is required",
+            "bad color",
+        ]));
+        let client = ImageChartsClient::with_transport(signed_config(), mock.clone());
+
+        let err = client.to_buffer(&signed_chart()).await.unwrap_err();
+        assert_eq!(mock.request_count(), 2);
+        assert_eq!(err.status_code, Some(400));
+        assert_eq!(err.code.as_deref(), Some("IC_VALIDATION_ERROR"));
+        assert_eq!(err.validation_errors().len(), 2);
+        assert_eq!(err.message, "\"chs\" is required\nbad color");
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_client_retries_through_transport() {
+        let mock = MockTransport::new();
+        mock.push(MockResponse::failure(crate::ImageChartsErrorKind::Timeout));
+        mock.push(MockResponse::image(OutputFormat::Png, vec![1, 2, 3]));
+        let client = BlockingImageChartsClient::with_transport(signed_config(), mock.clone());
+
+        assert_eq!(client.to_buffer(&signed_chart()).unwrap(), vec![1, 2, 3]);
+        assert_eq!(mock.request_count(), 2);
+
+        mock.push(MockResponse::error(403, "IC_INVALID_SIGNATURE"));
+        let err = client.to_buffer(&signed_chart()).unwrap_err();
+        assert_eq!(err.kind, crate::ImageChartsErrorKind::Http { status: 403 });
+        assert_eq!(err.code.as_deref(), Some("IC_INVALID_SIGNATURE"));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_client_streams_to_async_writer_and_file() {
+        let mock = MockTransport::new();
+        mock.set_fallback(MockResponse::image(OutputFormat::Gif, "GIF89a"));
+        let client = ImageChartsClient::with_transport(ImageChartsConfig::default(), mock);
+        let chart = ImageCharts::new().cht("p").chs("100x100");
+
+        let mut image = Vec::new();
+        let format = client.to_async_writer(&chart, &mut image).await.unwrap();
+        assert_eq!(
+            (format, image.as_slice()),
+            (OutputFormat::Gif, &b"GIF89a"[..])
+        );
+
+        let dir = TempDir::new("async-to-file");
+        client.to_file(&chart, dir.join("chart")).await.unwrap();
+        assert_eq!(dir.files(), ["chart.gif"]);
+        assert_eq!(std::fs::read(dir.join("chart.gif")).unwrap(), b"GIF89a");
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_client_serves_downloads_from_cache() {
+        let cache = Arc::new(crate::MemoryCache::new(10));
+        let mock = MockTransport::new();
+        mock.set_fallback(MockResponse::image(OutputFormat::Svg, "<svg/>"));
+        let config = ImageChartsConfig {
+            cache: Some(cache.clone()),
+            ..signed_config()
+        };
+        let client = ImageChartsClient::with_transport(config, mock.clone());
+
+        let image = client.to_buffer(&signed_chart()).await.unwrap();
+        assert_eq!(client.to_buffer(&signed_chart()).await.unwrap(), image);
+        let mut streamed = Vec::new();
+        let format = client
+            .to_async_writer(&signed_chart(), &mut streamed)
+            .await
+            .unwrap();
+        assert_eq!((format, streamed), (OutputFormat::Svg, image));
+        assert_eq!(mock.request_count(), 1);
+        assert_eq!(cache.len(), 1);
+
+        client
+            .to_buffer(&signed_chart().chs("200x200"))
+            .await
+            .unwrap();
+        assert_eq!(mock.request_count(), 2);
+    }
+
+    /// Answers after a delay, so that identical calls overlap
+    #[cfg(feature = "async")]
+    struct Slow(MockTransport);
+
+    #[cfg(feature = "async")]
+    impl Transport for Slow {
+        fn send(
+            &self,
+            request: TransportRequest,
+        ) -> crate::BoxFuture<'_, Result<TransportResponse, ImageChartsError>> {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Transport::send(&self.0, request).await
+            })
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_client_coalesces_identical_downloads() {
+        let mock = MockTransport::new();
+        mock.push(MockResponse::image(OutputFormat::Png, vec![1, 2, 3]));
+        mock.push(MockResponse::error(400, "IC_INVALID_DATA"));
+        let client = ImageChartsClient::with_transport(signed_config(), Slow(mock.clone()));
+
+        let spawn_all = |chart: ImageCharts| {
+            (0..10)
+                .map(|_| {
+                    let (client, chart) = (client.clone(), chart.clone());
+                    tokio::spawn(async move { client.to_buffer(&chart).await })
+                })
+                .collect::<Vec<_>>()
+        };
+        for task in spawn_all(signed_chart()) {
+            assert_eq!(task.await.unwrap().unwrap(), vec![1, 2, 3]);
+        }
+        assert_eq!(mock.request_count(), 1);
+
+        for task in spawn_all(signed_chart().chd("t:fail")) {
+            let err = task.await.unwrap().unwrap_err();
+            assert_eq!(err.code.as_deref(), Some("IC_INVALID_DATA"));
+        }
+        assert_eq!(mock.request_count(), 2);
+        assert!(client.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_shared_clients_coalesce_identical_downloads() {
+        let mock = MockTransport::new();
+        mock.set_fallback(MockResponse::image(OutputFormat::Png, vec![1, 2, 3]));
+        let config = ImageChartsConfig {
+            host: "shared-in-flight.invalid".to_string(),
+            ..signed_config()
+        };
+        let client = || ImageChartsClient::with_transport(config.clone(), Slow(mock.clone()));
+        let chart = signed_chart();
+
+        let (first, second) = (client().sharing_in_flight(), client().sharing_in_flight());
+        let (first, second) = tokio::join!(first.to_buffer(&chart), second.to_buffer(&chart));
+        assert_eq!(
+            (first.unwrap(), second.unwrap()),
+            (vec![1, 2, 3], vec![1, 2, 3])
+        );
+        assert_eq!(mock.request_count(), 1);
+
+        let (first, second) = (client(), client().sharing_in_flight());
+        let (first, second) = tokio::join!(first.to_buffer(&chart), second.to_buffer(&chart));
+        assert!(first.is_ok() && second.is_ok());
+        assert_eq!(mock.request_count(), 3);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_client_streams_to_writer() {
+        let mock = MockTransport::new();
+        mock.push(MockResponse::error(503, "IC_UNAVAILABLE"));
+        mock.push(MockResponse::image(OutputFormat::Svg, "<svg/>"));
+        let client = BlockingImageChartsClient::with_transport(signed_config(), mock.clone());
+
+        let mut image = Vec::new();
+        let format = client.to_writer(&signed_chart(), &mut image).unwrap();
+        assert_eq!(
+            (format, image.as_slice()),
+            (OutputFormat::Svg, &b"<svg/>"[..])
+        );
+        assert_eq!(mock.request_count(), 2);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_to_file_never_leaves_truncated_file() {
+        use crate::transport::Body;
+        use std::io::Read;
+
+        /// Answers half an image, then fails
+        struct Truncating;
+
+        impl BlockingTransport for Truncating {
+            fn send(
+                &self,
+                _request: TransportRequest,
+            ) -> Result<TransportResponse, ImageChartsError> {
+                let broken = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+                let body = std::io::Cursor::new(b"\x89PNG".to_vec()).chain(FailingReader(broken));
+                Ok(TransportResponse {
+                    status: 200,
+                    headers: HeaderMap::new(),
+                    body: Body::from_reader(body),
+                })
+            }
+        }
+
+        struct FailingReader(std::io::Error);
+
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(self.0.kind(), self.0.to_string()))
+            }
+        }
+
+        let dir = TempDir::new("blocking-to-file");
+        let path = dir.join("chart.png");
+        std::fs::write(&path, b"previous chart").unwrap();
+
+        let client = BlockingImageChartsClient::with_transport(signed_config(), Truncating);
+        let err = client.to_file(&signed_chart(), &path).unwrap_err();
+        assert_eq!(err.kind, crate::ImageChartsErrorKind::Transport);
+        assert_eq!(dir.files(), ["chart.png"]);
+        assert_eq!(std::fs::read(&path).unwrap(), b"previous chart");
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_client_awaits_rate_limiter() {
+        use crate::{Quota, RateLimiter};
+
+        let limiter = Arc::new(RateLimiter::new(Quota::new(1, Duration::from_millis(50))));
+        let mock = MockTransport::new();
+        mock.push(MockResponse::error(429, "IC_RATE_LIMITED").header("x-ratelimit-reset", "30"));
+        let client = BlockingImageChartsClient::with_transport(
+            ImageChartsConfig {
+                rate_limiter: Some(limiter.clone()),
+                ..Default::default()
+            },
+            mock,
+        );
+        let chart = ImageCharts::new().cht("p").chs("100x100");
+
+        let err = client
+            .to_buffer(&chart.clone().icac("throttled"))
+            .unwrap_err();
+        assert_eq!(err.status_code, Some(429));
+        assert!(limiter.reserve(Some("throttled")) > Duration::from_secs(29));
+
+        let start = std::time::Instant::now();
+        for _ in 0..3 {
+            client.to_buffer(&chart).unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
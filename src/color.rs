@@ -0,0 +1,415 @@
+//! Typed colors and series color lists for the `chco` parameter
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{ImageChartsError, ImageChartsErrorKind};
+
+/// RGB color with an alpha channel, serialized as `RRGGBB` or `RRGGBBAA`
+///
+/// Colors parse from hexadecimal (`#` optional, `RGB`, `RGBA`, `RRGGBB` or `RRGGBBAA`)
+/// and from CSS color names, case-insensitively.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ Color, ImageCharts };
+///
+/// let red: Color = "#f44336".parse().unwrap();
+/// assert_eq!(red, Color::rgb(0xF4, 0x43, 0x36));
+/// assert_eq!("steelblue".parse::<Color>().unwrap().to_string(), "4682B4");
+/// assert_eq!(red.with_alpha(0x80).to_string(), "F4433680");
+///
+/// let chart = ImageCharts::new().cht("p").chco(red);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    /// Red channel
+    pub r: u8,
+    /// Green channel
+    pub g: u8,
+    /// Blue channel
+    pub b: u8,
+    /// Alpha channel, 255 being opaque
+    pub a: u8,
+}
+
+impl Color {
+    /// Opaque black
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    /// Opaque white
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    /// Fully transparent black
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    /// Opaque color
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 255)
+    }
+
+    /// Color with an alpha channel, 255 being opaque
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Same color with another alpha channel
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Color named `name` in CSS, such as `rebeccapurple`, case-insensitively
+    pub fn named(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name == "transparent" {
+            return Some(Self::TRANSPARENT);
+        }
+        CSS_COLORS
+            .binary_search_by(|(css, _)| css.cmp(&name.as_str()))
+            .ok()
+            .map(|index| {
+                let [_, r, g, b] = CSS_COLORS[index].1.to_be_bytes();
+                Self::rgb(r, g, b)
+            })
+    }
+
+    fn from_hex(hex: &str) -> Option<Self> {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        let short = |i: usize| digit(i).map(|d| d * 17);
+        match hex.len() {
+            3 => Some(Self::rgb(short(0)?, short(1)?, short(2)?)),
+            4 => Some(Self::rgba(short(0)?, short(1)?, short(2)?, short(3)?)),
+            6 => Some(Self::rgb(byte(0)?, byte(2)?, byte(4)?)),
+            8 => Some(Self::rgba(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02X}{:02X}{:02X}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02X}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Color {
+    type Err = ImageChartsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let hex = s.strip_prefix('#');
+        hex.and_then(Self::from_hex)
+            .or_else(|| Self::from_hex(s))
+            .or_else(|| hex.is_none().then(|| Self::named(s)).flatten())
+            .ok_or_else(|| {
+                ImageChartsError::new(
+                    ImageChartsErrorKind::InvalidConfig,
+                    format!("Unknown color \"{}\"", s),
+                )
+            })
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_string()
+    }
+}
+
+/// Serialized as its `RRGGBB[AA]` string
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserialized from any string [`Color::from_str`] accepts
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|e: ImageChartsError| D::Error::custom(e.message))
+    }
+}
+
+/// Colors of each series for `chco`, optionally one color per data point
+///
+/// Series are separated by `,` and the colors of the points of one series by `|`.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ Color, ImageCharts, SeriesColors };
+///
+/// let colors = SeriesColors::new()
+///     .series(Color::rgb(0xF4, 0x43, 0x36))
+///     .points(["gold".parse().unwrap(), Color::BLACK]);
+/// assert_eq!(colors.to_string(), "F44336,FFD700|000000");
+///
+/// let chart = ImageCharts::new().cht("bvg").chco(colors);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeriesColors {
+    series: Vec<Vec<Color>>,
+}
+
+impl SeriesColors {
+    /// Empty color list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Color the next series with `color`
+    pub fn series(mut self, color: Color) -> Self {
+        self.series.push(vec![color]);
+        self
+    }
+
+    /// Color each point of the next series, cycling through `colors`
+    pub fn points(mut self, colors: impl IntoIterator<Item = Color>) -> Self {
+        self.series.push(colors.into_iter().collect());
+        self
+    }
+}
+
+impl FromIterator<Color> for SeriesColors {
+    fn from_iter<I: IntoIterator<Item = Color>>(colors: I) -> Self {
+        colors.into_iter().fold(Self::new(), Self::series)
+    }
+}
+
+impl fmt::Display for SeriesColors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, colors) in self.series.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            for (j, color) in colors.iter().enumerate() {
+                if j > 0 {
+                    f.write_str("|")?;
+                }
+                write!(f, "{}", color)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<SeriesColors> for String {
+    fn from(colors: SeriesColors) -> Self {
+        colors.to_string()
+    }
+}
+
+impl From<&SeriesColors> for String {
+    fn from(colors: &SeriesColors) -> Self {
+        colors.to_string()
+    }
+}
+
+/// CSS named colors, sorted by name
+const CSS_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blue", 0x0000FF),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("cyan", 0x00FFFF),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgray", 0xA9A9A9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xA9A9A9),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xADFF2F),
+    ("grey", 0x808080),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightblue", 0xADD8E6),
+    ("lightcoral", 0xF08080),
+    ("lightcyan", 0xE0FFFF),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightgray", 0xD3D3D3),
+    ("lightgreen", 0x90EE90),
+    ("lightgrey", 0xD3D3D3),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lightyellow", 0xFFFFE0),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("magenta", 0xFF00FF),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xFF0000),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("white", 0xFFFFFF),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellow", 0xFFFF00),
+    ("yellowgreen", 0x9ACD32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_and_names() {
+        let parse = |s: &str| s.parse::<Color>().map(|c| c.to_string()).ok();
+        assert_eq!(parse("f44336"), Some("F44336".to_string()));
+        assert_eq!(parse("#03A9F480"), Some("03A9F480".to_string()));
+        assert_eq!(parse("#fff"), Some("FFFFFF".to_string()));
+        assert_eq!(parse("0f08"), Some("00FF0088".to_string()));
+        assert_eq!(parse("RebeccaPurple"), Some("663399".to_string()));
+        assert_eq!(parse("transparent"), Some("00000000".to_string()));
+        // `bad` is short hex, never a CSS name
+        assert_eq!(parse("#bad"), Some("BBAADD".to_string()));
+        assert_eq!(parse("#red"), None);
+        assert_eq!(parse("f4433"), None);
+        assert_eq!(parse("blurple"), None);
+    }
+
+    #[test]
+    fn test_css_colors_are_sorted() {
+        assert!(CSS_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(CSS_COLORS.len(), 148);
+        for (name, _) in CSS_COLORS {
+            assert!(Color::named(name).is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_series_colors() {
+        let colors: SeriesColors = [Color::BLACK, Color::WHITE.with_alpha(0)]
+            .into_iter()
+            .collect();
+        assert_eq!(colors.to_string(), "000000,FFFFFF00");
+
+        let colors = SeriesColors::new()
+            .points([Color::rgb(1, 2, 3), Color::rgb(4, 5, 6)])
+            .series(Color::BLACK);
+        let chart = crate::ImageCharts::new().chs("100x100").chco(&colors);
+        assert_eq!(chart.query["chco"], "010203|040506,000000");
+        assert!(chart.validate().is_empty());
+    }
+}
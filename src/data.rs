@@ -0,0 +1,580 @@
+//! Typed chart data (`chd`) model and encoders
+
+use std::fmt;
+
+const SIMPLE_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const EXTENDED_ALPHABET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-.";
+
+/// Encoding used to serialize [`ChartData`] into the `chd` parameter
+///
+/// [Reference documentation](https://documentation.image-charts.com/reference/data-format/)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DataEncoding {
+    /// `t:` text format, values as decimal numbers, series separated by `|`
+    #[default]
+    Text,
+    /// `a:` text format with automatic scaling, series separated by `|`
+    AutoScaled,
+    /// `s:` simple format, one character per value in `0..=61`, series separated by `,`
+    Simple,
+    /// `e:` extended format, two characters per value in `0..=4095`, series separated by `,`
+    Extended,
+}
+
+impl DataEncoding {
+    /// Prefix written before the encoded series (e.g. `t:`)
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            DataEncoding::Text => "t:",
+            DataEncoding::AutoScaled => "a:",
+            DataEncoding::Simple => "s:",
+            DataEncoding::Extended => "e:",
+        }
+    }
+
+    /// Marker used for a missing value
+    pub fn missing_value(&self) -> &'static str {
+        match self {
+            DataEncoding::Text | DataEncoding::AutoScaled | DataEncoding::Simple => "_",
+            DataEncoding::Extended => "__",
+        }
+    }
+
+    /// Largest value the encoding can represent, `None` for the text formats
+    pub fn max_value(&self) -> Option<u16> {
+        match self {
+            DataEncoding::Text | DataEncoding::AutoScaled => None,
+            DataEncoding::Simple => Some(61),
+            DataEncoding::Extended => Some(4095),
+        }
+    }
+
+    /// Detect the encoding of an already serialized `chd` value
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image_charts::DataEncoding;
+    ///
+    /// assert_eq!(DataEncoding::from_chd("e:AA.."), Some(DataEncoding::Extended));
+    /// assert_eq!(DataEncoding::from_chd("60,40"), None);
+    /// ```
+    pub fn from_chd(chd: &str) -> Option<Self> {
+        [
+            DataEncoding::Text,
+            DataEncoding::AutoScaled,
+            DataEncoding::Simple,
+            DataEncoding::Extended,
+        ]
+        .into_iter()
+        .find(|encoding| chd.starts_with(encoding.prefix()))
+    }
+
+    fn series_separator(&self) -> &'static str {
+        match self {
+            DataEncoding::Text | DataEncoding::AutoScaled => "|",
+            DataEncoding::Simple | DataEncoding::Extended => ",",
+        }
+    }
+
+    fn value_separator(&self) -> &'static str {
+        match self {
+            DataEncoding::Text | DataEncoding::AutoScaled => ",",
+            DataEncoding::Simple | DataEncoding::Extended => "",
+        }
+    }
+}
+
+/// A value that can be used as a single chart data point
+///
+/// `None`, `NaN` and infinite values are all treated as missing values.
+pub trait IntoDataValue {
+    /// Convert into an optional data point
+    fn into_data_value(self) -> Option<f64>;
+}
+
+macro_rules! impl_into_data_value {
+    ($($t:ty),*) => {
+        $(
+            impl IntoDataValue for $t {
+                fn into_data_value(self) -> Option<f64> {
+                    Some(self as f64)
+                }
+            }
+
+            impl IntoDataValue for &$t {
+                fn into_data_value(self) -> Option<f64> {
+                    Some(*self as f64)
+                }
+            }
+
+            impl IntoDataValue for Option<$t> {
+                fn into_data_value(self) -> Option<f64> {
+                    self.map(|v| v as f64)
+                }
+            }
+
+            impl IntoDataValue for &Option<$t> {
+                fn into_data_value(self) -> Option<f64> {
+                    self.map(|v| v as f64)
+                }
+            }
+        )*
+    };
+}
+
+impl_into_data_value!(f64, f32, i8, i16, i32, i64, u8, u16, u32, u64, usize, isize);
+
+/// Typed chart data, serialized into the `chd` parameter
+///
+/// Each series is a list of optional values; missing values are written with the
+/// marker of the selected [`DataEncoding`].
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ ChartData, ImageCharts };
+///
+/// let data = ChartData::new()
+///     .series([10.0, 20.5, -3.0])
+///     .series([Some(1.0), None, Some(3.0)]);
+/// assert_eq!(data.to_string(), "t:10,20.5,-3|1,_,3");
+///
+/// let url = ImageCharts::new().cht("lc").chd(data).chs("400x300").to_url();
+/// assert!(url.contains("chd=t%3A10%2C20.5%2C-3%7C1%2C_%2C3"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartData {
+    series: Vec<Vec<Option<f64>>>,
+    encoding: DataEncoding,
+    precision: Option<usize>,
+}
+
+impl ChartData {
+    /// Create an empty, text encoded, chart data
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build chart data from a list of series
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image_charts::ChartData;
+    ///
+    /// let data = ChartData::from_series(vec![vec![1, 2], vec![3, 4]]);
+    /// assert_eq!(data.to_string(), "t:1,2|3,4");
+    /// ```
+    pub fn from_series<S, I>(series: S) -> Self
+    where
+        S: IntoIterator<Item = I>,
+        I: IntoIterator,
+        I::Item: IntoDataValue,
+    {
+        series
+            .into_iter()
+            .fold(Self::new(), |data, values| data.series(values))
+    }
+
+    /// Append a data series
+    pub fn series<I>(mut self, values: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: IntoDataValue,
+    {
+        self.series.push(
+            values
+                .into_iter()
+                .map(|v| v.into_data_value().filter(|v| v.is_finite()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Set the encoding used when serializing
+    ///
+    /// With [`DataEncoding::Simple`] and [`DataEncoding::Extended`] values are rounded
+    /// to the nearest integer and must already be in the encoding range
+    /// (see [`DataEncoding::max_value`]); values outside of it are written as missing.
+    pub fn encoding(mut self, encoding: DataEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Round text encoded values to at most `decimals` decimal places
+    pub fn precision(mut self, decimals: usize) -> Self {
+        self.precision = Some(decimals);
+        self
+    }
+
+    /// Data series, missing values are `None`
+    pub fn get_series(&self) -> &[Vec<Option<f64>>] {
+        &self.series
+    }
+
+    /// Encoding used when serializing
+    pub fn get_encoding(&self) -> DataEncoding {
+        self.encoding
+    }
+
+    /// Number of data series
+    pub fn series_count(&self) -> usize {
+        self.series.len()
+    }
+
+    /// Pick the encoding producing the shortest URL while keeping every value within
+    /// `tolerance` (in data units) of its original value
+    ///
+    /// All series share a single scale going from the smallest value (or zero, if all
+    /// values are positive) to the largest one, which is how `a:` auto-scaling renders
+    /// them, so the chart looks the same whichever encoding wins. Text encodings are
+    /// rounded to the fewest decimals satisfying the tolerance; when two encodings
+    /// produce the same length the first of simple, extended, auto-scaled and text wins.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image_charts::{ ChartData, DataEncoding };
+    ///
+    /// let data = ChartData::new().series([0.0, 500.0, 1000.0]);
+    ///
+    /// let coarse = data.encode_smallest(10.0);
+    /// assert_eq!(coarse.encoding, DataEncoding::Simple);
+    /// assert_eq!(coarse.range, (0.0, 1000.0));
+    ///
+    /// let exact = data.encode_smallest(0.0);
+    /// assert_eq!(exact.chd, "a:0,500,1000");
+    /// ```
+    pub fn encode_smallest(&self, tolerance: f64) -> EncodedChartData {
+        let tolerance = if tolerance.is_nan() {
+            0.0
+        } else {
+            tolerance.max(0.0)
+        };
+        let range = self.range();
+
+        let mut best: Option<EncodedChartData> = None;
+        for encoding in [
+            DataEncoding::Simple,
+            DataEncoding::Extended,
+            DataEncoding::AutoScaled,
+            DataEncoding::Text,
+        ] {
+            let candidate = match encoding.max_value() {
+                Some(max) => self.encode_scaled(encoding, max, range, tolerance),
+                None => Some(self.encode_text(encoding, range, tolerance)),
+            };
+            if let Some(candidate) = candidate {
+                if best
+                    .as_ref()
+                    .is_none_or(|b| candidate.url_length() < b.url_length())
+                {
+                    best = Some(candidate);
+                }
+            }
+        }
+        best.expect("text encodings always satisfy the tolerance")
+    }
+
+    fn range(&self) -> (f64, f64) {
+        let values = self.series.iter().flatten().flatten().copied();
+        let (min, max) = values.fold((0.0_f64, f64::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(v), hi.max(v))
+        });
+        if max.is_finite() && max > min {
+            (min, max)
+        } else if max.is_finite() && max < 0.0 {
+            (max, 0.0)
+        } else {
+            (min, min + 1.0)
+        }
+    }
+
+    fn encode_scaled(
+        &self,
+        encoding: DataEncoding,
+        max_code: u16,
+        (lo, hi): (f64, f64),
+        tolerance: f64,
+    ) -> Option<EncodedChartData> {
+        let step = (hi - lo) / max_code as f64;
+        let mut scaled = ChartData::new().encoding(encoding);
+        for series in &self.series {
+            let mut codes = Vec::with_capacity(series.len());
+            for value in series {
+                let code = value.map(|v| ((v - lo) / step).round());
+                if let (Some(v), Some(code)) = (value, code) {
+                    if (lo + code * step - v).abs() > tolerance {
+                        return None;
+                    }
+                }
+                codes.push(code);
+            }
+            scaled = scaled.series(codes);
+        }
+        Some(EncodedChartData {
+            encoding,
+            chd: scaled.to_string(),
+            chds: None,
+            range: (lo, hi),
+        })
+    }
+
+    fn encode_text(
+        &self,
+        encoding: DataEncoding,
+        range: (f64, f64),
+        tolerance: f64,
+    ) -> EncodedChartData {
+        let values = || self.series.iter().flatten().flatten().copied();
+        let precision = (0..=15).find(|&decimals| {
+            values().all(|v| {
+                format_number(v, Some(decimals))
+                    .parse::<f64>()
+                    .is_ok_and(|rounded| (rounded - v).abs() <= tolerance)
+            })
+        });
+
+        let mut text = self.clone().encoding(encoding);
+        text.precision = precision;
+        let chds = (encoding == DataEncoding::Text).then(|| {
+            format!(
+                "{},{}",
+                format_number(range.0, None),
+                format_number(range.1, None)
+            )
+        });
+        EncodedChartData {
+            encoding,
+            chd: text.to_string(),
+            chds,
+            range,
+        }
+    }
+
+    fn encode_value(&self, value: Option<f64>, out: &mut String) {
+        let encoding = self.encoding;
+        match (encoding.max_value(), value) {
+            (_, None) => out.push_str(encoding.missing_value()),
+            (None, Some(v)) => out.push_str(&format_number(v, self.precision)),
+            (Some(max), Some(v)) => {
+                let rounded = v.round();
+                if !(0.0..=max as f64).contains(&rounded) {
+                    out.push_str(encoding.missing_value());
+                    return;
+                }
+                let code = rounded as usize;
+                if encoding == DataEncoding::Simple {
+                    out.push(SIMPLE_ALPHABET[code] as char);
+                } else {
+                    out.push(EXTENDED_ALPHABET[code / 64] as char);
+                    out.push(EXTENDED_ALPHABET[code % 64] as char);
+                }
+            }
+        }
+    }
+}
+
+/// Result of [`ChartData::encode_smallest`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedChartData {
+    /// Encoding that was selected
+    pub encoding: DataEncoding,
+    /// Serialized `chd` value
+    pub chd: String,
+    /// `chds` scaling required by the selected encoding, if any
+    pub chds: Option<String>,
+    /// Data range mapped to the bottom and top of the chart
+    pub range: (f64, f64),
+}
+
+impl EncodedChartData {
+    /// `chxr` value giving axis `axis_index` the same range as the data
+    pub fn axis_range(&self, axis_index: usize) -> String {
+        format!(
+            "{},{},{}",
+            axis_index,
+            format_number(self.range.0, None),
+            format_number(self.range.1, None)
+        )
+    }
+
+    fn url_length(&self) -> usize {
+        let chd = urlencoding::encode(&self.chd).len();
+        match self.chds {
+            Some(ref chds) => chd + "&chds=".len() + urlencoding::encode(chds).len(),
+            None => chd,
+        }
+    }
+}
+
+/// Format a number for the text formats: no exponent, no trailing zeros, no `-0`
+pub(crate) fn format_number(value: f64, precision: Option<usize>) -> String {
+    let formatted = match precision {
+        Some(decimals) => {
+            let s = format!("{:.*}", decimals, value);
+            if s.contains('.') {
+                s.trim_end_matches('0').trim_end_matches('.').to_string()
+            } else {
+                s
+            }
+        }
+        None => format!("{}", value),
+    };
+
+    if formatted.starts_with('-') && formatted[1..].chars().all(|c| c == '0' || c == '.') {
+        "0".to_string()
+    } else {
+        formatted
+    }
+}
+
+impl fmt::Display for ChartData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoding = self.encoding;
+        let mut out = String::from(encoding.prefix());
+        for (i, series) in self.series.iter().enumerate() {
+            if i > 0 {
+                out.push_str(encoding.series_separator());
+            }
+            for (j, value) in series.iter().enumerate() {
+                if j > 0 {
+                    out.push_str(encoding.value_separator());
+                }
+                self.encode_value(*value, &mut out);
+            }
+        }
+        f.write_str(&out)
+    }
+}
+
+impl From<ChartData> for String {
+    fn from(data: ChartData) -> Self {
+        data.to_string()
+    }
+}
+
+impl From<&ChartData> for String {
+    fn from(data: &ChartData) -> Self {
+        data.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_encoding() {
+        let data = ChartData::new().series([1.0, 2.5, 3.0]).series([4, 5, 6]);
+        assert_eq!(data.to_string(), "t:1,2.5,3|4,5,6");
+    }
+
+    #[test]
+    fn test_text_encoding_missing_values() {
+        let data = ChartData::new().series([Some(1.0), None, Some(f64::NAN), Some(f64::INFINITY)]);
+        assert_eq!(data.to_string(), "t:1,_,_,_");
+    }
+
+    #[test]
+    fn test_text_encoding_negative_and_precision() {
+        let data = ChartData::new()
+            .series([-0.0, -1.25, 0.1 + 0.2, -0.0001])
+            .precision(2);
+        assert_eq!(data.to_string(), "t:0,-1.25,0.3,0");
+    }
+
+    #[test]
+    fn test_auto_scaled_encoding() {
+        let data = ChartData::new()
+            .series([-100.0, 200.5, 75.55, 110.0])
+            .encoding(DataEncoding::AutoScaled);
+        assert_eq!(data.to_string(), "a:-100,200.5,75.55,110");
+    }
+
+    #[test]
+    fn test_simple_encoding() {
+        let data = ChartData::new()
+            .series([
+                Some(0.0),
+                Some(25.0),
+                None,
+                Some(61.0),
+                Some(62.0),
+                Some(-1.0),
+            ])
+            .series([1.4, 1.6])
+            .encoding(DataEncoding::Simple);
+        assert_eq!(data.to_string(), "s:AZ_9__,BC");
+    }
+
+    #[test]
+    fn test_extended_encoding() {
+        let data = ChartData::new()
+            .series([Some(0.0), Some(4095.0), None, Some(64.0), Some(4096.0)])
+            .encoding(DataEncoding::Extended);
+        assert_eq!(data.to_string(), "e:AA..__BA__");
+    }
+
+    #[test]
+    fn test_from_chd() {
+        assert_eq!(DataEncoding::from_chd("t:1,2"), Some(DataEncoding::Text));
+        assert_eq!(
+            DataEncoding::from_chd("a:1,2"),
+            Some(DataEncoding::AutoScaled)
+        );
+        assert_eq!(DataEncoding::from_chd("s:AB"), Some(DataEncoding::Simple));
+        assert_eq!(DataEncoding::from_chd(""), None);
+    }
+
+    #[test]
+    fn test_encode_smallest_simple_within_tolerance() {
+        let data = ChartData::new().series([0.0, 30.5, 61.0]);
+        let encoded = data.encode_smallest(0.5);
+        assert_eq!(encoded.encoding, DataEncoding::Simple);
+        assert_eq!(encoded.chd, "s:Af9");
+        assert_eq!(encoded.chds, None);
+        assert_eq!(encoded.axis_range(1), "1,0,61");
+    }
+
+    #[test]
+    fn test_encode_smallest_extended_when_simple_too_coarse() {
+        let data = ChartData::new().series((0..40).map(|i| i as f64 * 102.375));
+        let encoded = data.encode_smallest(1.0);
+        assert_eq!(encoded.encoding, DataEncoding::Extended);
+        assert_eq!(encoded.range, (0.0, 39.0 * 102.375));
+    }
+
+    #[test]
+    fn test_encode_smallest_text_rounding() {
+        let data = ChartData::new()
+            .series([Some(-1.04), None, Some(2.51)])
+            .series([0.0]);
+        let exact = data.encode_smallest(0.0);
+        assert_eq!(exact.encoding, DataEncoding::AutoScaled);
+        assert_eq!(exact.chd, "a:-1.04,_,2.51|0");
+        assert_eq!(exact.range, (-1.04, 2.51));
+
+        let rounded = data.encode_text(DataEncoding::Text, data.range(), 0.05);
+        assert_eq!(rounded.chd, "t:-1,_,2.5|0");
+        assert_eq!(rounded.chds.as_deref(), Some("-1.04,2.51"));
+    }
+
+    #[test]
+    fn test_encode_smallest_handles_flat_and_empty_data() {
+        let flat = ChartData::new().series([5.0, 5.0]).encode_smallest(0.0);
+        assert_eq!(flat.range, (0.0, 5.0));
+        let negative = ChartData::new().series([-5.0, -5.0]).encode_smallest(0.0);
+        assert_eq!(negative.range, (-5.0, 0.0));
+        let empty = ChartData::new().encode_smallest(1.0);
+        assert_eq!(empty.range, (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_from_series() {
+        let rows: Vec<&[f64]> = vec![&[1.0, 2.0], &[3.0]];
+        assert_eq!(ChartData::from_series(rows).to_string(), "t:1,2|3");
+    }
+}
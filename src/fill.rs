@@ -0,0 +1,270 @@
+//! Typed background, chart area, series and slice fills for the `chf` parameter
+
+use std::fmt;
+
+use crate::data::format_number;
+use crate::Color;
+
+/// Area of the chart painted by a fill
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FillTarget {
+    /// Whole image background (`bg`)
+    Background,
+    /// Chart area, inside the axes (`c`)
+    ChartArea,
+    /// Bars of the series at this index (`b<index>`)
+    Series(usize),
+    /// One slice of a pie series (`ps<series>-<slice>`)
+    Slice {
+        /// Index of the series
+        series: usize,
+        /// Index of the slice within the series
+        slice: usize,
+    },
+}
+
+impl fmt::Display for FillTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FillTarget::Background => f.write_str("bg"),
+            FillTarget::ChartArea => f.write_str("c"),
+            FillTarget::Series(index) => write!(f, "b{}", index),
+            FillTarget::Slice { series, slice } => write!(f, "ps{}-{}", series, slice),
+        }
+    }
+}
+
+/// Linear gradient, from color stop to color stop
+///
+/// The angle goes from 0 (horizontal, left to right) to 90 (vertical, bottom to top),
+/// and each stop sits at an offset from 0 to 1 along it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearGradient {
+    angle: f64,
+    stops: Vec<(Color, f64)>,
+}
+
+impl LinearGradient {
+    /// Gradient along `angle` degrees, starting with `color` at `offset`
+    pub fn new(angle: f64, color: Color, offset: f64) -> Self {
+        Self {
+            angle,
+            stops: vec![(color, offset)],
+        }
+    }
+
+    /// Reach `color` at `offset`, from 0 to 1
+    pub fn stop(mut self, color: Color, offset: f64) -> Self {
+        self.stops.push((color, offset));
+        self
+    }
+}
+
+/// Repeating stripes along an angle
+///
+/// The angle works as for [`LinearGradient`] and each stripe width is a fraction of the
+/// chart width, from 0 to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stripes {
+    angle: f64,
+    stripes: Vec<(Color, f64)>,
+}
+
+impl Stripes {
+    /// Stripes along `angle` degrees, starting with a stripe of `color`, `width` wide
+    pub fn new(angle: f64, color: Color, width: f64) -> Self {
+        Self {
+            angle,
+            stripes: vec![(color, width)],
+        }
+    }
+
+    /// Add a stripe of `color`, `width` wide
+    pub fn stripe(mut self, color: Color, width: f64) -> Self {
+        self.stripes.push((color, width));
+        self
+    }
+}
+
+/// How a [`FillTarget`] is painted
+#[derive(Debug, Clone, PartialEq)]
+pub enum FillStyle {
+    /// Single color (`s`)
+    Solid(Color),
+    /// Linear gradient (`lg`)
+    LinearGradient(LinearGradient),
+    /// Linear stripes (`ls`)
+    Stripes(Stripes),
+}
+
+impl From<Color> for FillStyle {
+    fn from(color: Color) -> Self {
+        FillStyle::Solid(color)
+    }
+}
+
+impl From<LinearGradient> for FillStyle {
+    fn from(gradient: LinearGradient) -> Self {
+        FillStyle::LinearGradient(gradient)
+    }
+}
+
+impl From<Stripes> for FillStyle {
+    fn from(stripes: Stripes) -> Self {
+        FillStyle::Stripes(stripes)
+    }
+}
+
+impl fmt::Display for FillStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (style, angle, pairs) = match self {
+            FillStyle::Solid(color) => return write!(f, "s,{}", color),
+            FillStyle::LinearGradient(gradient) => ("lg", gradient.angle, &gradient.stops),
+            FillStyle::Stripes(stripes) => ("ls", stripes.angle, &stripes.stripes),
+        };
+        write!(f, "{},{}", style, format_number(angle, None))?;
+        for (color, value) in pairs {
+            write!(f, ",{},{}", color, format_number(*value, None))?;
+        }
+        Ok(())
+    }
+}
+
+/// Fills of the `chf` parameter, one per painted area
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ Color, Fill, ImageCharts, LinearGradient, Stripes };
+///
+/// let red = Color::rgb(0xF4, 0x43, 0x36);
+/// let blue = Color::rgb(0x03, 0xA9, 0xF4);
+/// let fill = Fill::new()
+///     .background(Color::BLACK)
+///     .series(0, LinearGradient::new(0.0, red, 0.3).stop(blue, 0.8))
+///     .chart_area(Stripes::new(90.0, Color::WHITE, 0.2).stripe(blue.with_alpha(0x33), 0.2))
+///     .slice(0, 1, red);
+/// assert_eq!(
+///     fill.to_string(),
+///     "bg,s,000000|b0,lg,0,F44336,0.3,03A9F4,0.8|c,ls,90,FFFFFF,0.2,03A9F433,0.2|ps0-1,s,F44336"
+/// );
+///
+/// let chart = ImageCharts::new().cht("bvs").chf(fill);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fill {
+    fills: Vec<(FillTarget, FillStyle)>,
+}
+
+impl Fill {
+    /// No fill, the API defaults apply
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Paint `target` with `style`
+    pub fn fill(mut self, target: FillTarget, style: impl Into<FillStyle>) -> Self {
+        self.fills.push((target, style.into()));
+        self
+    }
+
+    /// Paint the whole image background
+    pub fn background(self, style: impl Into<FillStyle>) -> Self {
+        self.fill(FillTarget::Background, style)
+    }
+
+    /// Paint the chart area, inside the axes
+    pub fn chart_area(self, style: impl Into<FillStyle>) -> Self {
+        self.fill(FillTarget::ChartArea, style)
+    }
+
+    /// Paint the bars of the series at `index`
+    pub fn series(self, index: usize, style: impl Into<FillStyle>) -> Self {
+        self.fill(FillTarget::Series(index), style)
+    }
+
+    /// Paint the slice at `slice` of the pie series at `series`
+    pub fn slice(self, series: usize, slice: usize, style: impl Into<FillStyle>) -> Self {
+        self.fill(FillTarget::Slice { series, slice }, style)
+    }
+}
+
+impl fmt::Display for Fill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (target, style)) in self.fills.iter().enumerate() {
+            if i > 0 {
+                f.write_str("|")?;
+            }
+            write!(f, "{},{}", target, style)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Fill> for String {
+    fn from(fill: Fill) -> Self {
+        fill.to_string()
+    }
+}
+
+impl From<&Fill> for String {
+    fn from(fill: &Fill) -> Self {
+        fill.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageCharts;
+
+    #[test]
+    fn test_fill_serialization() {
+        let red = "f44336".parse().unwrap();
+        let blue = "03a9f4".parse().unwrap();
+        let fill = Fill::new().series(0, LinearGradient::new(0.0, red, 0.3).stop(blue, 0.8));
+        assert_eq!(fill.to_string(), "b0,lg,0,F44336,0.3,03A9F4,0.8");
+
+        let fill = Fill::new()
+            .background(Color::WHITE)
+            .chart_area(LinearGradient::new(45.5, red, 0.0).stop(blue, 1.0))
+            .fill(
+                FillTarget::Slice {
+                    series: 1,
+                    slice: 12,
+                },
+                Color::BLACK,
+            );
+        assert_eq!(
+            fill.to_string(),
+            "bg,s,FFFFFF|c,lg,45.5,F44336,0,03A9F4,1|ps1-12,s,000000"
+        );
+        assert_eq!(Fill::new().to_string(), "");
+    }
+
+    #[test]
+    fn test_fill_passes_validation() {
+        let fill = Fill::new()
+            .background(Color::BLACK.with_alpha(0x80))
+            .series(
+                2,
+                Stripes::new(90.0, Color::WHITE, 0.25).stripe(Color::BLACK, 0.25),
+            );
+        let chart = ImageCharts::new().chs("100x100").chf(&fill);
+        assert_eq!(
+            chart.query["chf"],
+            "bg,s,00000080|b2,ls,90,FFFFFF,0.25,000000,0.25"
+        );
+        assert!(chart.validate().is_empty());
+    }
+
+    #[test]
+    fn test_single_stop_fills_pass_validation() {
+        let fill = Fill::new()
+            .background(LinearGradient::new(45.0, Color::WHITE, 0.0))
+            .chart_area(Stripes::new(0.0, Color::BLACK, 0.5));
+        let chart = ImageCharts::new().chs("100x100").chf(&fill);
+        assert_eq!(chart.query["chf"], "bg,lg,45,FFFFFF,0|c,ls,0,000000,0.5");
+        assert!(chart.validate().is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+//! Typed legends emitting `chdl`, `chdlp` and `chdls` together
+
+use std::fmt;
+
+use crate::{Color, ImageCharts, ImageChartsError, ImageChartsErrorKind};
+
+/// Legend text color written when only the font size is set, as the API defaults to
+const DEFAULT_TEXT_COLOR: Color = Color::BLACK;
+
+/// Legend position written when only the order is set, as the API defaults to
+const DEFAULT_POSITION: LegendPosition = LegendPosition::Right;
+
+/// Where the legend is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LegendPosition {
+    /// Above the chart, entries side by side (`t`)
+    Top,
+    /// Above the chart, entries stacked (`tv`)
+    TopVertical,
+    /// Below the chart, entries side by side (`b`)
+    Bottom,
+    /// Below the chart, entries stacked (`bv`)
+    BottomVertical,
+    /// Left of the chart, entries stacked (`l`)
+    Left,
+    /// Right of the chart, entries stacked (`r`)
+    Right,
+}
+
+impl LegendPosition {
+    /// Value of this position in `chdlp`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LegendPosition::Top => "t",
+            LegendPosition::TopVertical => "tv",
+            LegendPosition::Bottom => "b",
+            LegendPosition::BottomVertical => "bv",
+            LegendPosition::Left => "l",
+            LegendPosition::Right => "r",
+        }
+    }
+}
+
+/// Order of the legend entries
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LegendOrder {
+    /// Order of the labels (`l`)
+    AsGiven,
+    /// Reverse order of the labels (`r`)
+    Reverse,
+    /// Order picked by the API for the chart type (`a`)
+    Auto,
+    /// Entries at these label indices, in this order
+    Custom(Vec<usize>),
+}
+
+impl fmt::Display for LegendOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LegendOrder::AsGiven => f.write_str("l"),
+            LegendOrder::Reverse => f.write_str("r"),
+            LegendOrder::Auto => f.write_str("a"),
+            LegendOrder::Custom(indices) => {
+                let indices: Vec<String> = indices.iter().map(usize::to_string).collect();
+                f.write_str(&indices.join(","))
+            }
+        }
+    }
+}
+
+/// Chart legend: one label per series, position, order and text style
+///
+/// Applied with [`ImageCharts::legend`]. Labels are joined with `|`, which has no
+/// escape in `chdl`, so [`Legend::new`] rejects labels containing one. Spaces and `+`
+/// are kept as typed, [`ImageCharts::to_url`] percent-encodes them.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ Color, ImageCharts, Legend, LegendOrder, LegendPosition };
+///
+/// let legend = Legend::new(["North America", "Europe", "Asia"])
+///     .unwrap()
+///     .position(LegendPosition::BottomVertical)
+///     .order(LegendOrder::Reverse)
+///     .color(Color::rgb(0x9E, 0x9E, 0x9E))
+///     .font_size(14);
+/// let chart = ImageCharts::new().cht("bvs").legend(legend);
+///
+/// let url = chart.to_url();
+/// assert!(url.contains("chdl=North%20America%7CEurope%7CAsia"));
+/// assert!(url.contains("chdlp=bv%7Cr"));
+/// assert!(url.contains("chdls=9E9E9E%2C14"));
+///
+/// let err = Legend::new(["Revenue | Q1"]).unwrap_err();
+/// assert_eq!(err.message, "Legend label \"Revenue | Q1\" contains \"|\", the \"chdl\" label separator");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Legend {
+    labels: Vec<String>,
+    position: Option<LegendPosition>,
+    order: Option<LegendOrder>,
+    color: Option<Color>,
+    font_size: Option<u32>,
+}
+
+impl Legend {
+    /// Legend with one label per series, in series order
+    ///
+    /// Fails with [`ImageChartsErrorKind::InvalidConfig`] when a label contains `|`.
+    pub fn new<I, S>(labels: I) -> Result<Self, ImageChartsError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let labels: Vec<String> = labels.into_iter().map(Into::into).collect();
+        if let Some(label) = labels.iter().find(|label| label.contains('|')) {
+            return Err(ImageChartsError::new(
+                ImageChartsErrorKind::InvalidConfig,
+                format!(
+                    "Legend label \"{}\" contains \"|\", the \"chdl\" label separator",
+                    label
+                ),
+            ));
+        }
+        Ok(Self {
+            labels,
+            ..Default::default()
+        })
+    }
+
+    /// Where the legend is drawn
+    pub fn position(mut self, position: LegendPosition) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Order of the legend entries
+    pub fn order(mut self, order: LegendOrder) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    /// Color of the legend text
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Font size of the legend text, in pixels
+    pub fn font_size(mut self, size: u32) -> Self {
+        self.font_size = Some(size);
+        self
+    }
+
+    fn chdl(&self) -> String {
+        self.labels.join("|")
+    }
+
+    fn chdlp(&self) -> Option<String> {
+        match (self.position, &self.order) {
+            (None, None) => None,
+            (Some(position), None) => Some(position.as_str().to_string()),
+            (position, Some(order)) => Some(format!(
+                "{}|{}",
+                position.unwrap_or(DEFAULT_POSITION).as_str(),
+                order
+            )),
+        }
+    }
+
+    fn chdls(&self) -> Option<String> {
+        match (self.color, self.font_size) {
+            (None, None) => None,
+            (Some(color), None) => Some(color.to_string()),
+            (color, Some(size)) => {
+                Some(format!("{},{}", color.unwrap_or(DEFAULT_TEXT_COLOR), size))
+            }
+        }
+    }
+}
+
+impl ImageCharts {
+    /// Set the legend, replacing any `chdl`, `chdlp` and `chdls` already set
+    pub fn legend(self, legend: Legend) -> Self {
+        let mut chart = self.clone_with("chdl", legend.chdl());
+        for (key, value) in [("chdlp", legend.chdlp()), ("chdls", legend.chdls())] {
+            match value {
+                Some(value) => chart = chart.clone_with(key, value),
+                None => {
+                    chart.query.remove(key);
+                }
+            }
+        }
+        chart
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ValidationErrorKind;
+
+    #[test]
+    fn test_labels_are_escaped() {
+        let chart = ImageCharts::new().legend(Legend::new(["C + D", "50%", ""]).unwrap());
+        assert_eq!(chart.query["chdl"], "C + D|50%|");
+        assert!(chart.to_url().contains("chdl=C%20%2B%20D%7C50%25%7C"));
+
+        let err = Legend::new(["A", "B|C"]).unwrap_err();
+        assert_eq!(err.kind, ImageChartsErrorKind::InvalidConfig);
+    }
+
+    #[test]
+    fn test_position_order_and_style() {
+        let legend = Legend::new(["a", "b", "c"]).unwrap();
+        assert_eq!(legend.chdlp(), None);
+        assert_eq!(legend.chdls(), None);
+        assert_eq!(
+            legend.clone().position(LegendPosition::Top).chdlp(),
+            Some("t".into())
+        );
+        assert_eq!(
+            legend
+                .clone()
+                .order(LegendOrder::Custom(vec![2, 0, 1]))
+                .chdlp(),
+            Some("r|2,0,1".into())
+        );
+        assert_eq!(
+            legend.clone().color(Color::WHITE).chdls(),
+            Some("FFFFFF".into())
+        );
+        assert_eq!(legend.font_size(9).chdls(), Some("000000,9".into()));
+    }
+
+    #[test]
+    fn test_labels_are_validated_against_series() {
+        let chart = ImageCharts::new()
+            .chs("100x100")
+            .chd("t:1|2")
+            .chdls("FF0000,20")
+            .legend(Legend::new(["x", "y"]).unwrap());
+        assert!(!chart.query.contains_key("chdls"));
+        assert!(chart.validate().is_empty());
+
+        let errors = chart.legend(Legend::new(["x"]).unwrap()).validate();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ValidationErrorKind::SeriesLabelMismatch);
+    }
+}
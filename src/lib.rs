@@ -11,6 +11,8 @@
 //! - `async` (default): Async API using tokio and reqwest
 //! - `blocking`: Blocking/synchronous API using reqwest blocking
 //! - `full`: Both async and blocking APIs
+//! - `test-util`: [`MockTransport`], an in-memory transport to test code using the clients offline
+//! - `test-server`: [`TestServer`], a local HTTP stand-in for the API, for integration tests
 //!
 //! # Example
 //!
@@ -27,11 +29,96 @@
 //! ```
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 
+mod axis;
+#[cfg(any(feature = "async", feature = "blocking"))]
+mod batch;
+mod cache;
+mod chart_type;
+mod color;
+#[cfg(any(feature = "async", feature = "blocking"))]
+mod client;
+mod data;
+mod fill;
+mod legend;
+mod marker;
+#[cfg(any(test, feature = "test-util"))]
+mod mock;
+mod output;
+mod rate_limit;
+mod retry;
+mod signature;
+mod spec;
+#[cfg(test)]
+mod temp_dir;
+mod template;
+mod title;
+#[cfg(any(test, feature = "test-server"))]
+mod test_server;
+mod theme;
+mod transport;
+mod url;
+mod validation;
+
+pub use axis::{Axis, AxisSide, TickStyle};
+pub use cache::{CachedChart, ChartCache, FileCache, MemoryCache};
+pub use chart_type::{ChartType, GraphVizEngine};
+pub use color::{Color, SeriesColors};
+#[cfg(feature = "blocking")]
+pub use client::BlockingImageChartsClient;
+#[cfg(feature = "async")]
+pub use client::ImageChartsClient;
+pub use data::{ChartData, DataEncoding, EncodedChartData, IntoDataValue};
+pub use fill::{Fill, FillStyle, FillTarget, LinearGradient, Stripes};
+pub use legend::{Legend, LegendOrder, LegendPosition};
+pub use marker::{Marker, MarkerPoints, MarkerShape};
+#[cfg(feature = "test-util")]
+pub use mock::{MockResponse, MockTransport};
+pub use output::OutputFormat;
+pub use rate_limit::{Quota, RateLimiter};
+pub use retry::RetryPolicy;
+pub use signature::{verify_signature, SignatureError};
+pub use spec::{ChartSpec, UnknownKeys};
+pub use template::ChartTemplate;
+pub use title::{Font, FontStyle, Title};
+#[cfg(feature = "test-server")]
+pub use test_server::TestServer;
+pub use theme::{Margins, Theme};
+#[cfg(feature = "blocking")]
+pub use transport::{BlockingReqwestTransport, BlockingTransport};
+pub use transport::{Body, BoxFuture, TransportRequest, TransportResponse};
+#[cfg(feature = "async")]
+pub use transport::{ReqwestTransport, Transport};
+pub use validation::{ValidationError, ValidationErrorKind, MAX_CHART_DIMENSION};
+
+/// Category of an [`ImageChartsError`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ImageChartsErrorKind {
+    /// The request could not be sent or the response could not be read
+    Transport,
+    /// The request did not complete within the configured timeout
+    Timeout,
+    /// The API answered with a non-success HTTP status
+    Http {
+        /// HTTP status code
+        status: u16,
+    },
+    /// Chart parameters were rejected, by the API or by local validation
+    Validation(Vec<ValidationError>),
+    /// Reading or writing a file failed
+    Io,
+    /// An enterprise signature could not be verified
+    Signing,
+    /// The configuration or an input (URL, chart type...) is invalid
+    InvalidConfig,
+}
+
 /// Error type for ImageCharts operations
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 #[error("{message}")]
 pub struct ImageChartsError {
     /// Error message
@@ -40,14 +127,33 @@ pub struct ImageChartsError {
     pub code: Option<String>,
     /// HTTP status code
     pub status_code: Option<u16>,
+    /// Category of the error
+    pub kind: ImageChartsErrorKind,
+    #[source]
+    source: Option<Arc<dyn std::error::Error + Send + Sync>>,
 }
 
 impl ImageChartsError {
-    fn new(message: impl Into<String>) -> Self {
+    fn new(kind: ImageChartsErrorKind, message: impl Into<String>) -> Self {
         Self {
             message: message.into(),
             code: None,
             status_code: None,
+            kind,
+            source: None,
+        }
+    }
+
+    fn with_source(mut self, source: impl std::error::Error + Send + Sync + 'static) -> Self {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    /// Every validation problem, empty unless the error is a validation error
+    pub fn validation_errors(&self) -> &[ValidationError] {
+        match self.kind {
+            ImageChartsErrorKind::Validation(ref errors) => errors,
+            _ => &[],
         }
     }
 
@@ -62,11 +168,6 @@ impl ImageChartsError {
     }
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
-struct ValidationError {
-    message: String,
-}
-
 /// Configuration for ImageCharts client
 #[derive(Debug, Clone)]
 pub struct ImageChartsConfig {
@@ -84,6 +185,14 @@ pub struct ImageChartsConfig {
     pub secret: Option<String>,
     /// Custom user-agent string
     pub user_agent: Option<String>,
+    /// Run [`ImageCharts::validate`] before each download and fail without any network call
+    pub validate: bool,
+    /// Retry policy for transient download failures
+    pub retry: RetryPolicy,
+    /// Rate limiter awaited before each request, shared by every clone of the configuration
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Cache of rendered charts consulted before each request, see [`ChartCache`]
+    pub cache: Option<Arc<dyn ChartCache>>,
 }
 
 impl Default for ImageChartsConfig {
@@ -96,6 +205,10 @@ impl Default for ImageChartsConfig {
             timeout: Duration::from_millis(5000),
             secret: None,
             user_agent: None,
+            validate: false,
+            retry: RetryPolicy::default(),
+            rate_limiter: None,
+            cache: None,
         }
     }
 }
@@ -204,6 +317,20 @@ impl ImageCharts {
         new_instance
     }
 
+    /// Append `value` to the list in parameter `key`, joined by `separator`
+    fn appended(self, key: &str, separator: &str, value: Option<String>) -> Self {
+        let Some(value) = value else {
+            return self;
+        };
+        match self.query.get(key).filter(|current| !current.is_empty()) {
+            Some(current) => {
+                let value = format!("{}{}{}", current, separator, value);
+                self.clone_with(key, value)
+            }
+            None => self.clone_with(key, value),
+        }
+    }
+
     
         /// bvg= grouped bar chart, bvs= stacked bar chart, lc=line chart, ls=sparklines, p=pie chart. gv=graph viz
     ///          Three-dimensional pie chart (p3) will be rendered in 2D, concentric pie chart are not supported.
@@ -222,6 +349,11 @@ impl ImageCharts {
     /// use image_charts::ImageCharts;
     /// let chart = ImageCharts::new().cht("p");
     /// ```
+    ///
+    /// ```rust
+    /// use image_charts::{ ChartType, ImageCharts };
+    /// let chart = ImageCharts::new().cht(ChartType::Pie);
+    /// ```
     pub fn cht(self, value: impl Into<String>) -> Self {
         self.clone_with("cht", value)
     }
@@ -240,6 +372,11 @@ impl ImageCharts {
     /// use image_charts::ImageCharts;
     /// let chart = ImageCharts::new().chd("t:10,20,30|15,25,35");
     /// ```
+    ///
+    /// ```rust
+    /// use image_charts::{ ChartData, ImageCharts };
+    /// let chart = ImageCharts::new().chd(ChartData::new().series([10, 20, 30]).series([15, 25, 35]));
+    /// ```
     pub fn chd(self, value: impl Into<String>) -> Self {
         self.clone_with("chd", value)
     }
@@ -324,6 +461,11 @@ impl ImageCharts {
     /// let chart = ImageCharts::new().chof(".svg");
     /// ```
     ///
+    /// ```rust
+    /// use image_charts::{ ImageCharts, OutputFormat };
+    /// let chart = ImageCharts::new().chof(OutputFormat::Gif);
+    /// ```
+    ///
     /// Default: `".png"`
     pub fn chof(self, value: impl Into<String>) -> Self {
         self.clone_with("chof", value)
@@ -711,6 +853,73 @@ impl ImageCharts {
     }
     
 
+    /// Set chart data using the encoding producing the shortest URL
+    ///
+    /// Values stay within `tolerance` (in data units) of the original ones, see
+    /// [`ChartData::encode_smallest`]. The matching `chds` scaling is set (and any
+    /// previous one removed) and, when a `y` axis was declared with `chxt` and no
+    /// `chxr` is set yet, that axis gets the data range so labels stay accurate.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image_charts::{ ChartData, DataEncoding, ImageCharts };
+    ///
+    /// let data = ChartData::new().series([120.0, 480.0, 960.0]);
+    /// let chart = ImageCharts::new()
+    ///     .cht("lc")
+    ///     .chs("400x300")
+    ///     .chxt("x,y")
+    ///     .chd_auto(&data, 10.0);
+    ///
+    /// assert_eq!(chart.data_encoding(), Some(DataEncoding::Simple));
+    /// assert!(chart.to_url().contains("chxr=1%2C0%2C960"));
+    /// ```
+    pub fn chd_auto(self, data: &ChartData, tolerance: f64) -> Self {
+        let encoded = data.encode_smallest(tolerance);
+        let mut chart = self.clone_with("chd", encoded.chd.clone());
+        chart.query.remove("chds");
+        if let Some(ref chds) = encoded.chds {
+            chart = chart.clone_with("chds", chds.clone());
+        }
+
+        let y_axis = chart
+            .query
+            .get("chxt")
+            .and_then(|chxt| chxt.split(',').position(|axis| axis.trim() == "y"));
+        match y_axis {
+            Some(index) if !chart.query.contains_key("chxr") => {
+                chart.clone_with("chxr", encoded.axis_range(index))
+            }
+            _ => chart,
+        }
+    }
+
+    /// Encoding of the current `chd` parameter, useful to inspect what [`ImageCharts::chd_auto`]
+    /// picked before calling [`ImageCharts::to_url`]
+    pub fn data_encoding(&self) -> Option<DataEncoding> {
+        self.query.get("chd").and_then(|chd| DataEncoding::from_chd(chd))
+    }
+
+    /// Chart type of the current `cht` parameter, `None` if unset or unknown
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image_charts::{ ChartType, ImageCharts };
+    ///
+    /// let chart = ImageCharts::new().cht("bvg");
+    /// assert_eq!(chart.chart_type(), Some(ChartType::BarVerticalGrouped));
+    /// ```
+    pub fn chart_type(&self) -> Option<ChartType> {
+        self.query.get("cht").and_then(|cht| cht.parse().ok())
+    }
+
+    /// Output format of the current `chof` parameter, `None` if unset or unknown
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        self.query.get("chof").and_then(|chof| chof.parse().ok())
+    }
+
     /// Get the full Image-Charts API URL (signed and encoded if necessary)
     ///
     /// This method returns the complete URL that can be used to fetch the chart image.
@@ -731,14 +940,7 @@ impl ImageCharts {
     /// assert!(url.starts_with("https://image-charts.com/chart?"));
     /// ```
     pub fn to_url(&self) -> String {
-        let mut pairs: Vec<(&String, &String)> = self.query.iter().collect();
-        pairs.sort_by(|a, b| a.0.cmp(b.0));
-
-        let mut query_string = pairs
-            .iter()
-            .map(|(k, v)| format!("{}={}", k, urlencoding::encode(v)))
-            .collect::<Vec<_>>()
-            .join("&");
+        let mut query_string = self.canonical_query();
 
         if self.query.contains_key("icac") {
             if let Some(ref secret) = self.config.secret {
@@ -765,6 +967,18 @@ impl ImageCharts {
         )
     }
 
+    /// Query string sorted by parameter name, as signed by [`ImageCharts::to_url`]
+    fn canonical_query(&self) -> String {
+        let mut pairs: Vec<(&String, &String)> = self.query.iter().collect();
+        pairs.sort_by(|a, b| a.0.cmp(b.0));
+
+        pairs
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, urlencoding::encode(v)))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
     fn sign(&self, data: &str, secret: &str) -> String {
         use hmac::{Hmac, Mac};
         use sha2::Sha256;
@@ -777,20 +991,21 @@ impl ImageCharts {
         hex::encode(result.into_bytes())
     }
 
-    fn get_mime_type(&self) -> &str {
-        if self.query.contains_key("chan") {
-            "image/gif"
+    /// Format expected from `chof`, or from `chan` (GIF) when `chof` is unset, PNG otherwise
+    fn inferred_output_format(&self) -> OutputFormat {
+        self.output_format().unwrap_or(if self.query.contains_key("chan") {
+            OutputFormat::Gif
         } else {
-            "image/png"
-        }
+            OutputFormat::Png
+        })
+    }
+
+    fn get_mime_type(&self) -> &str {
+        self.inferred_output_format().mime_type()
     }
 
     fn get_file_format(&self) -> &str {
-        if self.query.contains_key("chan") {
-            "gif"
-        } else {
-            "png"
-        }
+        self.inferred_output_format().extension()
     }
 
     fn build_user_agent(&self) -> String {
@@ -810,21 +1025,25 @@ impl ImageCharts {
         error_code: Option<String>,
         validation_header: Option<&str>,
     ) -> ImageChartsError {
-        let validation_message = validation_header
-            .and_then(|v| serde_json::from_str::<Vec<ValidationError>>(v).ok())
-            .map(|errors| {
-                errors
-                    .into_iter()
-                    .map(|e| e.message)
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            });
+        let validation_errors = validation_header
+            .and_then(|v| serde_json::from_str::<Vec<ValidationError>>(v).ok());
+        let validation_message = validation_errors.as_ref().map(|errors| {
+            errors
+                .iter()
+                .map(|e| e.message.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
 
         let message = validation_message
             .or_else(|| error_code.clone())
             .unwrap_or_else(|| format!("HTTP {}", status));
+        let kind = match validation_errors {
+            Some(errors) => ImageChartsErrorKind::Validation(errors),
+            None => ImageChartsErrorKind::Http { status },
+        };
 
-        let mut err = ImageChartsError::new(message).with_status(status);
+        let mut err = ImageChartsError::new(kind, message).with_status(status);
         if let Some(code) = error_code {
             err = err.with_code(code);
         }
@@ -837,6 +1056,9 @@ impl ImageCharts {
 impl ImageCharts {
     /// Do an async request to Image-Charts API and return the image as bytes
     ///
+    /// A new connection is opened on every call, use [`ImageChartsClient`] to render
+    /// many charts. Identical concurrent calls share a single request.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
@@ -856,53 +1078,16 @@ impl ImageCharts {
     /// }
     /// ```
     pub async fn to_buffer(&self) -> Result<Vec<u8>, ImageChartsError> {
-        let client = reqwest::Client::builder()
-            .timeout(self.config.timeout)
-            .build()
-            .map_err(|e| ImageChartsError::new(e.to_string()))?;
-
-        let response = client
-            .get(self.to_url())
-            .header("User-Agent", self.build_user_agent())
-            .send()
+        ImageChartsClient::shared(self.config.clone())?
+            .to_buffer(self)
             .await
-            .map_err(|e| {
-                let mut err = ImageChartsError::new(e.to_string());
-                if let Some(status) = e.status() {
-                    err = err.with_status(status.as_u16());
-                }
-                err
-            })?;
-
-        let status = response.status().as_u16();
-        if (200..300).contains(&status) {
-            response
-                .bytes()
-                .await
-                .map(|b| b.to_vec())
-                .map_err(|e| ImageChartsError::new(e.to_string()).with_status(status))
-        } else {
-            let error_code = response
-                .headers()
-                .get("x-ic-error-code")
-                .and_then(|v| v.to_str().ok())
-                .map(String::from);
-            let validation_header = response
-                .headers()
-                .get("x-ic-error-validation")
-                .and_then(|v| v.to_str().ok())
-                .map(String::from);
-
-            Err(Self::parse_error_response(
-                status,
-                error_code,
-                validation_header.as_deref(),
-            ))
-        }
     }
 
     /// Do an async request and write the image to a file
     ///
+    /// When `path` has no extension, the one matching the image format is appended. The
+    /// image is streamed to a temporary file, renamed to `path` once complete.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
@@ -922,15 +1107,49 @@ impl ImageCharts {
     /// }
     /// ```
     pub async fn to_file(&self, path: impl AsRef<std::path::Path>) -> Result<(), ImageChartsError> {
-        let buffer = self.to_buffer().await?;
-        tokio::fs::write(path, buffer)
+        ImageChartsClient::with_config(self.config.clone())?
+            .to_file(self, path)
+            .await
+    }
+
+    /// Do an async request and stream the image to `writer`
+    ///
+    /// The image is written chunk by chunk as it is received rather than collected in
+    /// memory first. Returns the format of the image.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use image_charts::ImageCharts;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut file = tokio::fs::File::create("chart.png").await?;
+    ///     let format = ImageCharts::new()
+    ///         .cht("p")
+    ///         .chd("t:60,40")
+    ///         .chs("100x100")
+    ///         .to_async_writer(&mut file)
+    ///         .await?;
+    ///
+    ///     println!("Streamed a {} image", format.mime_type());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn to_async_writer<W>(&self, writer: &mut W) -> Result<OutputFormat, ImageChartsError>
+    where
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        ImageChartsClient::with_config(self.config.clone())?
+            .to_async_writer(self, writer)
             .await
-            .map_err(|e| ImageChartsError::new(e.to_string()))
     }
 
-    /// Do an async request and return a base64-encoded data URI
+    /// Do an async request and return a data URI
     ///
-    /// The returned string can be used directly in HTML `<img>` tags or CSS.
+    /// The returned string can be used directly in HTML `<img>` tags or CSS. The MIME
+    /// type follows the response `Content-Type`; SVG images are URL-encoded rather than
+    /// base64-encoded. Identical concurrent calls share a single request.
     ///
     /// # Example
     ///
@@ -951,10 +1170,9 @@ impl ImageCharts {
     /// }
     /// ```
     pub async fn to_data_uri(&self) -> Result<String, ImageChartsError> {
-        use base64::{engine::general_purpose::STANDARD, Engine as _};
-        let buffer = self.to_buffer().await?;
-        let encoded = STANDARD.encode(&buffer);
-        Ok(format!("data:{};base64,{}", self.get_mime_type(), encoded))
+        ImageChartsClient::shared(self.config.clone())?
+            .to_data_uri(self)
+            .await
     }
 }
 
@@ -963,6 +1181,9 @@ impl ImageCharts {
 impl ImageCharts {
     /// Do a blocking request to Image-Charts API and return the image as bytes
     ///
+    /// A new connection is opened on every call, use [`BlockingImageChartsClient`] to
+    /// render many charts.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
@@ -980,51 +1201,14 @@ impl ImageCharts {
     /// }
     /// ```
     pub fn to_buffer_blocking(&self) -> Result<Vec<u8>, ImageChartsError> {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(self.config.timeout)
-            .build()
-            .map_err(|e| ImageChartsError::new(e.to_string()))?;
-
-        let response = client
-            .get(self.to_url())
-            .header("User-Agent", self.build_user_agent())
-            .send()
-            .map_err(|e| {
-                let mut err = ImageChartsError::new(e.to_string());
-                if let Some(status) = e.status() {
-                    err = err.with_status(status.as_u16());
-                }
-                err
-            })?;
-
-        let status = response.status().as_u16();
-        if (200..300).contains(&status) {
-            response
-                .bytes()
-                .map(|b| b.to_vec())
-                .map_err(|e| ImageChartsError::new(e.to_string()).with_status(status))
-        } else {
-            let error_code = response
-                .headers()
-                .get("x-ic-error-code")
-                .and_then(|v| v.to_str().ok())
-                .map(String::from);
-            let validation_header = response
-                .headers()
-                .get("x-ic-error-validation")
-                .and_then(|v| v.to_str().ok())
-                .map(String::from);
-
-            Err(Self::parse_error_response(
-                status,
-                error_code,
-                validation_header.as_deref(),
-            ))
-        }
+        BlockingImageChartsClient::with_config(self.config.clone())?.to_buffer(self)
     }
 
     /// Do a blocking request and write the image to a file
     ///
+    /// When `path` has no extension, the one matching the image format is appended. The
+    /// image is streamed to a temporary file, renamed to `path` once complete.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
@@ -1045,11 +1229,37 @@ impl ImageCharts {
         &self,
         path: impl AsRef<std::path::Path>,
     ) -> Result<(), ImageChartsError> {
-        let buffer = self.to_buffer_blocking()?;
-        std::fs::write(path, buffer).map_err(|e| ImageChartsError::new(e.to_string()))
+        BlockingImageChartsClient::with_config(self.config.clone())?.to_file(self, path)
+    }
+
+    /// Do a blocking request and stream the image to `writer`
+    ///
+    /// The image is written chunk by chunk as it is received rather than collected in
+    /// memory first. Returns the format of the image.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use image_charts::ImageCharts;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut stdout = std::io::stdout().lock();
+    ///     ImageCharts::new()
+    ///         .cht("p")
+    ///         .chd("t:60,40")
+    ///         .chs("100x100")
+    ///         .to_writer(&mut stdout)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn to_writer<W>(&self, writer: &mut W) -> Result<OutputFormat, ImageChartsError>
+    where
+        W: std::io::Write + ?Sized,
+    {
+        BlockingImageChartsClient::with_config(self.config.clone())?.to_writer(self, writer)
     }
 
-    /// Do a blocking request and return a base64-encoded data URI
+    /// Do a blocking request and return a data URI, see [`ImageCharts::to_data_uri`]
     ///
     /// # Example
     ///
@@ -1068,10 +1278,7 @@ impl ImageCharts {
     /// }
     /// ```
     pub fn to_data_uri_blocking(&self) -> Result<String, ImageChartsError> {
-        use base64::{engine::general_purpose::STANDARD, Engine as _};
-        let buffer = self.to_buffer_blocking()?;
-        let encoded = STANDARD.encode(&buffer);
-        Ok(format!("data:{};base64,{}", self.get_mime_type(), encoded))
+        BlockingImageChartsClient::with_config(self.config.clone())?.to_data_uri(self)
     }
 }
 
@@ -1103,6 +1310,10 @@ pub struct ImageChartsBuilder {
     timeout: Option<Duration>,
     secret: Option<String>,
     user_agent: Option<String>,
+    validate: bool,
+    retry: Option<RetryPolicy>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    cache: Option<Arc<dyn ChartCache>>,
 }
 
 impl ImageChartsBuilder {
@@ -1148,10 +1359,51 @@ impl ImageChartsBuilder {
         self
     }
 
+    /// Validate parameters offline before each download, see [`ImageCharts::validate`]
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Set the retry policy for transient download failures
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Wait for `rate_limiter` before each request, see [`RateLimiter`]
+    pub fn rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Serve downloads from `cache` when possible, see [`ChartCache`]
+    pub fn cache(mut self, cache: Arc<dyn ChartCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     /// Build the ImageCharts instance
     pub fn build(self) -> ImageCharts {
+        ImageCharts::with_config(self.build_config())
+    }
+
+    /// Build a reusable async client, see [`ImageChartsClient`]
+    #[cfg(feature = "async")]
+    pub fn build_client(self) -> Result<ImageChartsClient, ImageChartsError> {
+        ImageChartsClient::with_config(self.build_config())
+    }
+
+    /// Build a reusable blocking client, see [`BlockingImageChartsClient`]
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking_client(self) -> Result<BlockingImageChartsClient, ImageChartsError> {
+        BlockingImageChartsClient::with_config(self.build_config())
+    }
+
+    /// Build the ImageChartsConfig
+    pub fn build_config(self) -> ImageChartsConfig {
         let default = ImageChartsConfig::default();
-        ImageCharts::with_config(ImageChartsConfig {
+        ImageChartsConfig {
             protocol: self.protocol.unwrap_or(default.protocol),
             host: self.host.unwrap_or(default.host),
             port: self.port.unwrap_or(default.port),
@@ -1159,26 +1411,42 @@ impl ImageChartsBuilder {
             timeout: self.timeout.unwrap_or(default.timeout),
             secret: self.secret,
             user_agent: self.user_agent,
-        })
+            validate: self.validate,
+            retry: self.retry.unwrap_or(default.retry),
+            rate_limiter: self.rate_limiter,
+            cache: self.cache,
+        }
     }
 }
 
+/// Join fields the API reads by position, such as those of `chxs` or `chts`
+///
+/// A field can only be given along with every field before it: unset fields up to the
+/// last set one are written with their default, the ones after it are left out. `None`
+/// when no field is set.
+pub(crate) fn positional_fields<const N: usize>(
+    fields: [(Option<String>, &str); N],
+) -> Option<String> {
+    let last = fields.iter().rposition(|(value, _)| value.is_some())?;
+    let fields: Vec<String> = fields
+        .into_iter()
+        .take(last + 1)
+        .map(|(value, default)| value.unwrap_or_else(|| default.to_string()))
+        .collect();
+    Some(fields.join(","))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn create_image_charts() -> ImageCharts {
-        match std::env::var("IMAGE_CHARTS_USER_AGENT") {
-            Ok(ua) => ImageCharts::builder().user_agent(ua).build(),
-            Err(_) => ImageCharts::new(),
-        }
-    }
-
-    fn create_image_charts_with_secret(secret: &str) -> ImageCharts {
-        match std::env::var("IMAGE_CHARTS_USER_AGENT") {
-            Ok(ua) => ImageCharts::builder().secret(secret).user_agent(ua).build(),
-            Err(_) => ImageCharts::with_secret(secret),
-        }
+    #[test]
+    fn test_positional_fields() {
+        assert_eq!(positional_fields([(None, "a"), (None, "b")]), None);
+        assert_eq!(
+            positional_fields([(None, "a"), (Some("x".to_string()), "b"), (None, "c")]),
+            Some("a,x".to_string())
+        );
     }
 
     #[test]
@@ -1267,6 +1535,29 @@ mod tests {
         assert!(url.contains("chs=300x200"));
     }
 
+    #[test]
+    fn test_parse_error_response_kinds() {
+        let err = ImageCharts::parse_error_response(
+            400,
+            Some("IC_VALIDATION_ERROR".to_string()),
+            Some(r#"[{"message":"\"chs\" is required"},{"message":"\"cht\" is invalid"}]"#),
+        );
+        assert_eq!(err.message, "\"chs\" is required\n\"cht\" is invalid");
+        assert_eq!(err.code.as_deref(), Some("IC_VALIDATION_ERROR"));
+        assert_eq!(err.status_code, Some(400));
+        let messages: Vec<_> = err
+            .validation_errors()
+            .iter()
+            .map(|e| e.message.as_str())
+            .collect();
+        assert_eq!(messages, vec!["\"chs\" is required", "\"cht\" is invalid"]);
+
+        let err = ImageCharts::parse_error_response(503, None, None);
+        assert_eq!(err.kind, ImageChartsErrorKind::Http { status: 503 });
+        assert_eq!(err.message, "HTTP 503");
+        assert!(err.validation_errors().is_empty());
+    }
+
     #[test]
     fn test_get_mime_type_png() {
         let chart = ImageCharts::new().cht("p").chs("100x100");
@@ -1279,22 +1570,66 @@ mod tests {
         assert_eq!(chart.get_mime_type(), "image/gif");
     }
 
+    #[test]
+    fn test_get_mime_type_svg() {
+        let chart = ImageCharts::new().cht("qr").chs("100x100").chof(".svg");
+        assert_eq!(chart.get_mime_type(), "image/svg+xml");
+        assert_eq!(chart.get_file_format(), "svg");
+    }
+
+    #[test]
+    fn test_chd_auto_sets_scaling() {
+        let data = ChartData::new().series([-1.04, 0.3, 2.51]);
+        let chart = ImageCharts::new()
+            .chds("0,100")
+            .chxt("x,y")
+            .chd_auto(&data, 0.0);
+        assert_eq!(chart.data_encoding(), Some(DataEncoding::AutoScaled));
+        assert_eq!(chart.query.get("chd").unwrap(), "a:-1.04,0.3,2.51");
+        assert!(!chart.query.contains_key("chds"));
+        assert_eq!(chart.query.get("chxr").unwrap(), "1,-1.04,2.51");
+
+        let kept = ImageCharts::new()
+            .chxt("y")
+            .chxr("0,0,10")
+            .chd_auto(&data, 0.0);
+        assert_eq!(kept.query.get("chxr").unwrap(), "0,0,10");
+    }
+
     #[cfg(feature = "blocking")]
     mod blocking_tests {
         use super::*;
+        use crate::test_server::TestServer;
 
         #[test]
         fn test_to_buffer_blocking_rejects_without_chs() {
-            let result = create_image_charts().cht("p").chd("t:1,2,3").to_buffer_blocking();
-            assert!(result.is_err());
+            let server = TestServer::start().unwrap();
+            let result = ImageCharts::with_config(server.config())
+                .cht("p")
+                .chd("t:1,2,3")
+                .to_buffer_blocking();
+            assert_eq!(result.unwrap_err().status_code, Some(400));
         }
 
         #[test]
-        fn test_to_buffer_blocking_works() {
-            // Add delay to avoid rate limiting
-            std::thread::sleep(std::time::Duration::from_secs(3));
+        fn test_to_buffer_blocking_validates_offline() {
+            let result = ImageCharts::builder()
+                .host("unreachable.invalid")
+                .validate(true)
+                .build()
+                .cht("p")
+                .chd("t:1,2,3")
+                .to_buffer_blocking();
+            let err = result.unwrap_err();
+            assert_eq!(err.message, "\"chs\" is required");
+            assert_eq!(err.status_code, Some(400));
+            assert!(matches!(err.kind, ImageChartsErrorKind::Validation(_)));
+        }
 
-            let result = create_image_charts()
+        #[test]
+        fn test_to_buffer_blocking_works() {
+            let server = TestServer::start().unwrap();
+            let result = ImageCharts::with_config(server.config())
                 .cht("p")
                 .chd("t:1,2,3")
                 .chs("100x100")
@@ -1306,9 +1641,8 @@ mod tests {
 
         #[test]
         fn test_to_data_uri_blocking_works() {
-            std::thread::sleep(std::time::Duration::from_secs(3));
-
-            let result = create_image_charts()
+            let server = TestServer::start().unwrap();
+            let result = ImageCharts::with_config(server.config())
                 .cht("p")
                 .chd("t:1,2,3")
                 .chs("100x100")
@@ -1322,22 +1656,39 @@ mod tests {
     #[cfg(feature = "async")]
     mod async_tests {
         use super::*;
+        use crate::test_server::TestServer;
 
         #[tokio::test]
         async fn test_to_buffer_async_rejects_without_chs() {
-            let result = create_image_charts()
+            let server = TestServer::start().unwrap();
+            let result = ImageCharts::with_config(server.config())
                 .cht("p")
                 .chd("t:1,2,3")
                 .to_buffer()
                 .await;
-            assert!(result.is_err());
+            assert_eq!(result.unwrap_err().status_code, Some(400));
         }
 
         #[tokio::test]
-        async fn test_to_buffer_async_works() {
-            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+        async fn test_to_buffer_async_validates_offline() {
+            let result = ImageCharts::builder()
+                .host("unreachable.invalid")
+                .validate(true)
+                .build()
+                .cht("p")
+                .chd("t:1,2,3")
+                .to_buffer()
+                .await;
+            let err = result.unwrap_err();
+            assert_eq!(err.message, "\"chs\" is required");
+            assert_eq!(err.status_code, Some(400));
+            assert!(matches!(err.kind, ImageChartsErrorKind::Validation(_)));
+        }
 
-            let result = create_image_charts()
+        #[tokio::test]
+        async fn test_to_buffer_async_works() {
+            let server = TestServer::start().unwrap();
+            let result = ImageCharts::with_config(server.config())
                 .cht("p")
                 .chd("t:1,2,3")
                 .chs("100x100")
@@ -1350,9 +1701,8 @@ mod tests {
 
         #[tokio::test]
         async fn test_to_data_uri_async_works() {
-            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-
-            let result = create_image_charts()
+            let server = TestServer::start().unwrap();
+            let result = ImageCharts::with_config(server.config())
                 .cht("p")
                 .chd("t:1,2,3")
                 .chs("100x100")
@@ -1362,5 +1712,18 @@ mod tests {
             let data_uri = result.unwrap();
             assert!(data_uri.starts_with("data:image/png;base64,"));
         }
+
+        #[tokio::test]
+        async fn test_to_buffer_async_coalesces_identical_calls() {
+            let server = TestServer::start().unwrap();
+            let chart = ImageCharts::with_config(server.config())
+                .cht("p")
+                .chd("t:1,2,3")
+                .chs("100x100");
+            let (first, second) = tokio::join!(chart.to_buffer(), chart.to_data_uri());
+            assert!(!first.unwrap().is_empty());
+            assert!(second.unwrap().starts_with("data:image/png;base64,"));
+            assert_eq!(server.request_count(), 1);
+        }
     }
 }
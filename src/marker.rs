@@ -0,0 +1,311 @@
+//! Typed markers of compound charts, serialized into the `chm` parameter
+
+use std::fmt;
+
+use crate::data::format_number;
+use crate::{Color, ImageCharts};
+
+/// Data points a marker is drawn on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MarkerPoints {
+    /// Every point of the series (`-1`)
+    #[default]
+    All,
+    /// The point at this index (`n`)
+    Index(usize),
+    /// Every `step` points from `start` to `end` included (`start:end:step`)
+    Range {
+        /// Index of the first point
+        start: usize,
+        /// Index of the last point
+        end: usize,
+        /// Number of points between two markers
+        step: usize,
+    },
+}
+
+impl fmt::Display for MarkerPoints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkerPoints::All => f.write_str("-1"),
+            MarkerPoints::Index(index) => write!(f, "{}", index),
+            MarkerPoints::Range { start, end, step } => write!(f, "{}:{}:{}", start, end, step),
+        }
+    }
+}
+
+/// Shape drawn by a [`Marker::Shape`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarkerShape {
+    /// Arrow (`a`)
+    Arrow,
+    /// Cross (`c`)
+    Cross,
+    /// Rectangle (`C`)
+    Rectangle,
+    /// Diamond (`d`)
+    Diamond,
+    /// Circle (`o`)
+    Circle,
+    /// Square (`s`)
+    Square,
+    /// X shape (`x`)
+    X,
+    /// Horizontal line across the chart (`h`)
+    HorizontalLine,
+    /// Vertical line from the x axis to the point (`v`)
+    VerticalLine,
+}
+
+impl MarkerShape {
+    /// Marker type of this shape in `chm`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarkerShape::Arrow => "a",
+            MarkerShape::Cross => "c",
+            MarkerShape::Rectangle => "C",
+            MarkerShape::Diamond => "d",
+            MarkerShape::Circle => "o",
+            MarkerShape::Square => "s",
+            MarkerShape::X => "x",
+            MarkerShape::HorizontalLine => "h",
+            MarkerShape::VerticalLine => "v",
+        }
+    }
+}
+
+/// One marker of the `chm` parameter, added with [`ImageCharts::marker`]
+///
+/// Series are referred to by their index in `chd`, which [`ImageCharts::validate`]
+/// checks.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ Color, ImageCharts, Marker, MarkerPoints, MarkerShape };
+///
+/// let blue = Color::rgb(0x22, 0x4E, 0x99);
+/// let chart = ImageCharts::new()
+///     .cht("lc")
+///     .chs("700x300")
+///     .chd("t:10,30,20|40,60,50")
+///     .marker(Marker::FillBetween { color: blue.with_alpha(0x40), start: 0, end: 1 })
+///     .marker(Marker::ValueLabels {
+///         format: "*f1*".to_string(),
+///         color: Color::BLACK,
+///         series: 1,
+///         points: MarkerPoints::All,
+///         size: 12,
+///     })
+///     .marker(Marker::Shape {
+///         shape: MarkerShape::Circle,
+///         color: blue,
+///         series: 0,
+///         points: MarkerPoints::Index(1),
+///         size: 8,
+///     });
+///
+/// assert!(chart.validate().is_empty());
+/// assert!(chart.to_url().contains("chm=b%2C224E9940%2C0%2C1%2C0%7CN%2Af1%2A%2C000000%2C1%2C-1%2C12%7Co%2C224E99%2C0%2C1%2C8"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Marker {
+    /// Fill the area between two line series (`b`)
+    FillBetween {
+        /// Fill color
+        color: Color,
+        /// Index of the series the fill starts from
+        start: usize,
+        /// Index of the series the fill ends at
+        end: usize,
+    },
+    /// Fill the area below a line series, down to the x axis (`B`)
+    FillToBottom {
+        /// Fill color
+        color: Color,
+        /// Index of the series
+        series: usize,
+    },
+    /// Draw a series as a line over the chart, such as a line over bars (`D`)
+    Line {
+        /// Line color
+        color: Color,
+        /// Index of the series
+        series: usize,
+        /// Line width, in pixels
+        width: f64,
+    },
+    /// Label points with their value (`N`)
+    ValueLabels {
+        /// Number format, such as `*f2*`, `*p*` or `*cEUR*`
+        format: String,
+        /// Text color
+        color: Color,
+        /// Index of the series
+        series: usize,
+        /// Points labelled
+        points: MarkerPoints,
+        /// Font size, in pixels
+        size: u32,
+    },
+    /// Draw a shape on points
+    Shape {
+        /// Shape drawn
+        shape: MarkerShape,
+        /// Shape color
+        color: Color,
+        /// Index of the series
+        series: usize,
+        /// Points marked
+        points: MarkerPoints,
+        /// Shape size, in pixels
+        size: u32,
+    },
+}
+
+impl Marker {
+    /// Index of every series the marker refers to
+    pub fn series(&self) -> Vec<usize> {
+        match self {
+            Marker::FillBetween { start, end, .. } => vec![*start, *end],
+            Marker::FillToBottom { series, .. }
+            | Marker::Line { series, .. }
+            | Marker::ValueLabels { series, .. }
+            | Marker::Shape { series, .. } => vec![*series],
+        }
+    }
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Marker::FillBetween { color, start, end } => {
+                write!(f, "b,{},{},{},0", color, start, end)
+            }
+            Marker::FillToBottom { color, series } => write!(f, "B,{},{},0,0", color, series),
+            Marker::Line {
+                color,
+                series,
+                width,
+            } => write!(
+                f,
+                "D,{},{},0,{}",
+                color,
+                series,
+                format_number(*width, None)
+            ),
+            Marker::ValueLabels {
+                format,
+                color,
+                series,
+                points,
+                size,
+            } => write!(f, "N{},{},{},{},{}", format, color, series, points, size),
+            Marker::Shape {
+                shape,
+                color,
+                series,
+                points,
+                size,
+            } => write!(
+                f,
+                "{},{},{},{},{}",
+                shape.as_str(),
+                color,
+                series,
+                points,
+                size
+            ),
+        }
+    }
+}
+
+impl From<Marker> for String {
+    fn from(marker: Marker) -> Self {
+        marker.to_string()
+    }
+}
+
+impl ImageCharts {
+    /// Add a marker to `chm`, after the markers already set
+    pub fn marker(self, marker: Marker) -> Self {
+        self.appended("chm", "|", Some(marker.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ValidationErrorKind;
+
+    #[test]
+    fn test_marker_serialization() {
+        let red = Color::rgb(0xFF, 0, 0);
+        let markers = [
+            (
+                Marker::FillToBottom {
+                    color: red,
+                    series: 2,
+                },
+                "B,FF0000,2,0,0",
+            ),
+            (
+                Marker::Line {
+                    color: red,
+                    series: 1,
+                    width: 2.5,
+                },
+                "D,FF0000,1,0,2.5",
+            ),
+            (
+                Marker::Shape {
+                    shape: MarkerShape::Rectangle,
+                    color: red,
+                    series: 0,
+                    points: MarkerPoints::Range {
+                        start: 1,
+                        end: 9,
+                        step: 2,
+                    },
+                    size: 5,
+                },
+                "C,FF0000,0,1:9:2,5",
+            ),
+        ];
+        for (marker, chm) in markers {
+            assert_eq!(marker.to_string(), chm);
+        }
+    }
+
+    #[test]
+    fn test_markers_append_and_validate_series() {
+        let chart = ImageCharts::new()
+            .chs("100x100")
+            .chd("t:1,2|3,4")
+            .chm("o,000000,0,-1,5")
+            .marker(Marker::FillBetween {
+                color: Color::BLACK,
+                start: 0,
+                end: 2,
+            })
+            .marker(Marker::Line {
+                color: Color::WHITE,
+                series: 3,
+                width: 1.0,
+            });
+        assert_eq!(
+            chart.query["chm"],
+            "o,000000,0,-1,5|b,000000,0,2,0|D,FFFFFF,3,0,1"
+        );
+
+        let errors = chart.validate();
+        assert_eq!(
+            errors.iter().map(|e| e.kind).collect::<Vec<_>>(),
+            [ValidationErrorKind::MarkerSeriesNotFound; 2]
+        );
+        assert_eq!(
+            errors[0].message,
+            "\"chm\" marker \"b,000000,0,2,0\" refers to series 2 but \"chd\" has 2 series"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! In-memory transport answering canned responses, for tests without network
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+
+#[cfg(feature = "blocking")]
+use crate::transport::BlockingTransport;
+#[cfg(feature = "async")]
+use crate::transport::{BoxFuture, Transport};
+use crate::transport::{TransportRequest, TransportResponse};
+use crate::{ImageChartsError, ImageChartsErrorKind, OutputFormat};
+
+/// Canned answer of a [`MockTransport`]
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ MockResponse, OutputFormat };
+///
+/// let image = MockResponse::image(OutputFormat::Svg, "<svg/>");
+/// let throttled = MockResponse::error(429, "IC_RATE_LIMIT_EXCEEDED").header("retry-after", "1");
+/// let invalid = MockResponse::validation(&["\"chs\" is required"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub(crate) status: u16,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Vec<u8>,
+    pub(crate) failure: Option<ImageChartsErrorKind>,
+}
+
+impl MockResponse {
+    /// Response with the given status, no header and an empty body
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            failure: None,
+        }
+    }
+
+    /// `200` response carrying an image of the given format
+    pub fn image(format: OutputFormat, body: impl Into<Vec<u8>>) -> Self {
+        Self::new(200)
+            .header(CONTENT_TYPE.as_str(), format.mime_type())
+            .body(body)
+    }
+
+    /// Error response with an `x-ic-error-code` header
+    pub fn error(status: u16, code: &str) -> Self {
+        Self::new(status).header("x-ic-error-code", code)
+    }
+
+    /// `400` response with an `x-ic-error-validation` header listing `messages`
+    pub fn validation(messages: &[&str]) -> Self {
+        let errors = messages
+            .iter()
+            .map(|message| serde_json::json!({ "message": message }))
+            .collect::<Vec<_>>();
+        Self::error(400, "IC_VALIDATION_ERROR").header(
+            "x-ic-error-validation",
+            &serde_json::Value::Array(errors).to_string(),
+        )
+    }
+
+    /// Request failing before any response, such as a refused connection or a timeout
+    pub fn failure(kind: ImageChartsErrorKind) -> Self {
+        Self {
+            failure: Some(kind),
+            ..Self::new(0)
+        }
+    }
+
+    /// Add a response header
+    ///
+    /// # Panics
+    ///
+    /// Panics when `name` or `value` is not a valid header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let name = HeaderName::from_bytes(name.as_bytes()).expect("invalid header name");
+        let value = HeaderValue::from_str(value).expect("invalid header value");
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Set the response body
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub(crate) fn into_response(self) -> Result<TransportResponse, ImageChartsError> {
+        match self.failure {
+            Some(kind) => Err(ImageChartsError::new(kind, "Mock transport failure")),
+            None => Ok(TransportResponse {
+                status: self.status,
+                headers: self.headers,
+                body: self.body.into(),
+            }),
+        }
+    }
+}
+
+impl Default for MockResponse {
+    fn default() -> Self {
+        Self::image(OutputFormat::Png, Vec::new())
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: VecDeque<MockResponse>,
+    fallback: MockResponse,
+    requests: Vec<TransportRequest>,
+}
+
+/// Transport recording every request and answering queued [`MockResponse`]s
+///
+/// Responses are served in the order they were pushed. Once the queue is empty, the
+/// fallback response (an empty PNG by default) is served. Clones share their queue and
+/// recorded requests, so a clone can be handed to a client and inspected afterwards.
+/// Implements both [`Transport`](crate::Transport) and
+/// [`BlockingTransport`](crate::BlockingTransport).
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ ImageCharts, ImageChartsClient, ImageChartsConfig, MockResponse, MockTransport };
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mock = MockTransport::new();
+///     mock.push(MockResponse::error(403, "IC_INVALID_SIGNATURE"));
+///
+///     let client = ImageChartsClient::with_transport(ImageChartsConfig::default(), mock.clone());
+///     let err = client
+///         .to_buffer(&ImageCharts::new().cht("p").chs("100x100"))
+///         .await
+///         .unwrap_err();
+///
+///     assert_eq!(err.code.as_deref(), Some("IC_INVALID_SIGNATURE"));
+///     assert_eq!(mock.requests()[0].url, "https://image-charts.com/chart?chs=100x100&cht=p");
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTransport {
+    /// Mock answering an empty PNG to every request
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the response of a future request
+    pub fn push(&self, response: MockResponse) {
+        self.lock().responses.push_back(response);
+    }
+
+    /// Response served once the queue is empty
+    pub fn set_fallback(&self, response: MockResponse) {
+        self.lock().fallback = response;
+    }
+
+    /// Every request sent so far, oldest first
+    pub fn requests(&self) -> Vec<TransportRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// Number of requests sent so far
+    pub fn request_count(&self) -> usize {
+        self.lock().requests.len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn respond(&self, request: TransportRequest) -> Result<TransportResponse, ImageChartsError> {
+        let mut state = self.lock();
+        state.requests.push(request);
+        let response = match state.responses.pop_front() {
+            Some(response) => response,
+            None => state.fallback.clone(),
+        };
+        drop(state);
+        response.into_response()
+    }
+}
+
+#[cfg(feature = "async")]
+impl Transport for MockTransport {
+    fn send(
+        &self,
+        request: TransportRequest,
+    ) -> BoxFuture<'_, Result<TransportResponse, ImageChartsError>> {
+        Box::pin(async move { self.respond(request) })
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl BlockingTransport for MockTransport {
+    fn send(&self, request: TransportRequest) -> Result<TransportResponse, ImageChartsError> {
+        self.respond(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str) -> TransportRequest {
+        TransportRequest::new(url.to_string(), "test").unwrap()
+    }
+
+    #[test]
+    fn test_mock_serves_queue_then_fallback() {
+        let mock = MockTransport::new();
+        mock.push(MockResponse::error(503, "IC_BUSY"));
+        mock.set_fallback(MockResponse::image(OutputFormat::Gif, "GIF89a"));
+
+        let shared = mock.clone();
+        assert_eq!(shared.respond(request("https://h/1")).unwrap().status, 503);
+        let response = shared.respond(request("https://h/2")).unwrap();
+        assert_eq!(response.headers[CONTENT_TYPE], "image/gif");
+        assert_eq!(response.body.bytes_blocking().unwrap(), b"GIF89a");
+
+        let urls = mock
+            .requests()
+            .into_iter()
+            .map(|r| r.url)
+            .collect::<Vec<_>>();
+        assert_eq!(urls, ["https://h/1", "https://h/2"]);
+    }
+
+    #[test]
+    fn test_mock_validation_and_failure() {
+        let mock = MockTransport::new();
+        mock.push(MockResponse::validation(&["\"chs\" is required"]));
+        mock.push(MockResponse::failure(ImageChartsErrorKind::Timeout));
+
+        let response = mock.respond(request("https://h/")).unwrap();
+        assert_eq!(
+            response.headers["x-ic-error-validation"],
+            r#"[{"message":"\"chs\" is required"}]"#
+        );
+        let err = mock.respond(request("https://h/")).unwrap_err();
+        assert_eq!(err.kind, ImageChartsErrorKind::Timeout);
+        assert_eq!(mock.request_count(), 2);
+    }
+}
@@ -0,0 +1,173 @@
+//! Image output formats (`chof`) and data URI encoding
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{ImageChartsError, ImageChartsErrorKind};
+
+/// Image output format, serialized into the `chof` parameter
+///
+/// [Reference documentation](https://documentation.image-charts.com/reference/output-format/)
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ ImageCharts, OutputFormat };
+///
+/// let chart = ImageCharts::new().cht("qr").chl("hello").chs("100x100").chof(OutputFormat::Svg);
+/// assert_eq!(chart.output_format(), Some(OutputFormat::Svg));
+/// assert!(chart.to_url().contains("chof=.svg"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    /// `.png` image
+    Png,
+    /// `.svg` image, only supported by QR codes and GraphViz charts
+    Svg,
+    /// `.gif` image, used by animated charts (`chan`)
+    Gif,
+}
+
+impl OutputFormat {
+    /// `chof` value (e.g. `.png`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => ".png",
+            OutputFormat::Svg => ".svg",
+            OutputFormat::Gif => ".gif",
+        }
+    }
+
+    /// File extension, without leading dot
+    pub fn extension(&self) -> &'static str {
+        &self.as_str()[1..]
+    }
+
+    /// MIME type of the image
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Svg => "image/svg+xml",
+            OutputFormat::Gif => "image/gif",
+        }
+    }
+
+    /// Format matching a `Content-Type` header value, parameters such as `charset` ignored
+    pub fn from_mime_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        [OutputFormat::Png, OutputFormat::Svg, OutputFormat::Gif]
+            .into_iter()
+            .find(|format| mime.eq_ignore_ascii_case(format.mime_type()))
+    }
+
+    /// Data URI embedding `image`: URL-encoded for SVG, which keeps it compact and
+    /// readable, base64 otherwise
+    pub(crate) fn data_uri(&self, image: &[u8]) -> String {
+        use base64::{engine::general_purpose::STANDARD, Engine as _};
+        match self {
+            OutputFormat::Svg => format!("data:{},{}", self.mime_type(), encode_svg(image)),
+            _ => format!(
+                "data:{};base64,{}",
+                self.mime_type(),
+                STANDARD.encode(image)
+            ),
+        }
+    }
+}
+
+/// Percent-encode only what a data URI cannot hold as is
+fn encode_svg(svg: &[u8]) -> String {
+    let mut encoded = String::with_capacity(svg.len());
+    for &byte in svg {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'!'
+            | b'$'
+            | b'&'
+            | b'\''
+            | b'('
+            | b')'
+            | b'*'
+            | b'+'
+            | b','
+            | b';'
+            | b'='
+            | b':'
+            | b'@'
+            | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = ImageChartsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [OutputFormat::Png, OutputFormat::Svg, OutputFormat::Gif]
+            .into_iter()
+            .find(|format| format.as_str() == s)
+            .ok_or_else(|| {
+                ImageChartsError::new(
+                    ImageChartsErrorKind::InvalidConfig,
+                    format!("Unknown output format \"{}\"", s),
+                )
+            })
+    }
+}
+
+impl From<OutputFormat> for String {
+    fn from(format: OutputFormat) -> Self {
+        format.as_str().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output_format() {
+        assert_eq!(".svg".parse::<OutputFormat>().unwrap(), OutputFormat::Svg);
+        assert!("svg".parse::<OutputFormat>().is_err());
+        assert_eq!(OutputFormat::Gif.extension(), "gif");
+    }
+
+    #[test]
+    fn test_from_mime_type() {
+        assert_eq!(
+            OutputFormat::from_mime_type("image/svg+xml; charset=utf-8"),
+            Some(OutputFormat::Svg)
+        );
+        assert_eq!(
+            OutputFormat::from_mime_type("IMAGE/PNG"),
+            Some(OutputFormat::Png)
+        );
+        assert_eq!(OutputFormat::from_mime_type("text/html"), None);
+    }
+
+    #[test]
+    fn test_data_uri() {
+        assert_eq!(
+            OutputFormat::Png.data_uri(&[1, 2, 3]),
+            "data:image/png;base64,AQID"
+        );
+        assert_eq!(
+            OutputFormat::Svg.data_uri(r##"<svg fill="#fff">é 100%</svg>"##.as_bytes()),
+            "data:image/svg+xml,%3Csvg%20fill=%22%23fff%22%3E%C3%A9%20100%25%3C/svg%3E"
+        );
+    }
+}
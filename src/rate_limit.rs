@@ -0,0 +1,282 @@
+//! Client-side rate limiting of API requests, per account
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::header::HeaderMap;
+
+use crate::retry::parse_retry_after;
+
+/// Pause applied after a `429` response that does not say how long to wait
+const DEFAULT_PAUSE: Duration = Duration::from_secs(1);
+
+/// Number of requests allowed per period, in bursts of up to `requests`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    /// Requests allowed per period, which is also the largest burst
+    pub requests: u32,
+    /// Length of the period
+    pub period: Duration,
+}
+
+impl Quota {
+    /// `requests` requests every `period`
+    pub fn new(requests: u32, period: Duration) -> Self {
+        Self { requests, period }
+    }
+
+    /// `requests` requests per second
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// `requests` requests per minute
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// Tokens added to the bucket per second
+    fn rate(&self) -> f64 {
+        self.requests.max(1) as f64 / self.period.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Available tokens, negative when requests are already queued
+    tokens: f64,
+    updated: Instant,
+    paused_until: Option<Instant>,
+}
+
+/// Token-bucket rate limiter keyed by account id (`icac`)
+///
+/// Both download paths wait for a token before each request, retries included. Charts
+/// without `icac` share one anonymous bucket. When the API answers `429`, the account
+/// is paused for as long as its `Retry-After` or `X-RateLimit-Reset` header asks (one
+/// second otherwise) and its bucket is emptied, so requests resume at the quota rate
+/// rather than in a burst.
+///
+/// The limiter is stored in [`ImageChartsConfig::rate_limiter`](crate::ImageChartsConfig)
+/// behind an `Arc`: every chart and client cloned from that configuration shares it.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ ImageCharts, Quota, RateLimiter };
+/// use std::sync::Arc;
+///
+/// let limiter = Arc::new(
+///     RateLimiter::new(Quota::per_second(1)).account("enterprise_account", Quota::per_second(10)),
+/// );
+///
+/// let chart = ImageCharts::builder()
+///     .secret("secret")
+///     .rate_limiter(limiter)
+///     .build()
+///     .icac("enterprise_account");
+/// ```
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    default: Option<Quota>,
+    quotas: HashMap<String, Quota>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Limiter applying `quota` to every account without a quota of its own
+    pub fn new(quota: Quota) -> Self {
+        Self {
+            default: Some(quota),
+            ..Default::default()
+        }
+    }
+
+    /// Limiter only applying per-account quotas, and pauses after `429` responses
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Apply `quota` to the charts of account `icac`
+    pub fn account(mut self, icac: impl Into<String>, quota: Quota) -> Self {
+        self.quotas.insert(icac.into(), quota);
+        self
+    }
+
+    /// Quota applied to `account`, `None` when unlimited
+    pub fn quota(&self, account: Option<&str>) -> Option<Quota> {
+        account
+            .and_then(|account| self.quotas.get(account))
+            .or(self.default.as_ref())
+            .copied()
+    }
+
+    /// Take a token for `account` and return how long to wait before sending
+    pub(crate) fn reserve(&self, account: Option<&str>) -> Duration {
+        self.reserve_at(account, Instant::now())
+    }
+
+    fn reserve_at(&self, account: Option<&str>, now: Instant) -> Duration {
+        let key = account.unwrap_or_default();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(quota) = self.quota(account) else {
+            // Unlimited accounts only keep a bucket while paused by a `429`
+            let until = buckets.get(key).and_then(|bucket| bucket.paused_until);
+            let pause = until.map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+            if pause.is_zero() {
+                buckets.remove(key);
+            }
+            return pause;
+        };
+        let bucket = buckets.entry(key.to_string()).or_insert_with(|| Bucket {
+            tokens: quota.requests as f64,
+            updated: now,
+            paused_until: None,
+        });
+
+        let start = bucket
+            .paused_until
+            .filter(|until| *until > now)
+            .unwrap_or(now);
+        let rate = quota.rate();
+        let elapsed = start.saturating_duration_since(bucket.updated);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * rate).min(quota.requests.max(1) as f64) - 1.0;
+        bucket.updated = bucket.updated.max(start);
+        let wait = if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        };
+        start - now + wait
+    }
+
+    /// Pause `account` after a `429` response carrying `headers`
+    pub(crate) fn throttled(&self, account: Option<&str>, headers: &HeaderMap) {
+        self.throttled_at(account, headers, Instant::now())
+    }
+
+    fn throttled_at(&self, account: Option<&str>, headers: &HeaderMap, now: Instant) {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let pause = parse_retry_after(header("retry-after"))
+            .into_iter()
+            .chain(parse_reset(header("x-ratelimit-reset")))
+            .max()
+            .unwrap_or(DEFAULT_PAUSE);
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets
+            .entry(account.unwrap_or_default().to_string())
+            .or_insert_with(|| Bucket {
+                tokens: 0.0,
+                updated: now,
+                paused_until: None,
+            });
+        let until = now + pause;
+        bucket.paused_until = bucket.paused_until.max(Some(until));
+        bucket.tokens = bucket.tokens.min(0.0);
+        bucket.updated = bucket.updated.max(until);
+    }
+}
+
+/// Parse an `X-RateLimit-Reset` header, either seconds to wait or a Unix timestamp
+fn parse_reset(value: Option<&str>) -> Option<Duration> {
+    let seconds = value?.trim().parse::<u64>().ok()?;
+    // Values past 2001-09-09 can only be timestamps
+    if seconds < 1_000_000_000 {
+        return Some(Duration::from_secs(seconds));
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+    Some(Duration::from_secs(seconds).saturating_sub(now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_bucket_allows_burst_then_spaces_requests() {
+        let limiter = RateLimiter::new(Quota::per_second(2));
+        let now = Instant::now();
+        assert_eq!(limiter.reserve_at(None, now), Duration::ZERO);
+        assert_eq!(limiter.reserve_at(None, now), Duration::ZERO);
+        assert_eq!(limiter.reserve_at(None, now), ms(500));
+        assert_eq!(limiter.reserve_at(None, now), ms(1000));
+        // Tokens refill over time
+        assert_eq!(limiter.reserve_at(None, now + ms(2000)), ms(0));
+    }
+
+    #[test]
+    fn test_accounts_have_separate_buckets() {
+        let limiter = RateLimiter::unlimited().account("acme", Quota::per_minute(1));
+        let now = Instant::now();
+        assert_eq!(limiter.quota(Some("other")), None);
+        assert_eq!(limiter.reserve_at(Some("acme"), now), Duration::ZERO);
+        assert_eq!(
+            limiter.reserve_at(Some("acme"), now),
+            Duration::from_secs(60)
+        );
+        assert_eq!(limiter.reserve_at(Some("other"), now), Duration::ZERO);
+        assert_eq!(limiter.reserve_at(None, now), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_unlimited_accounts_keep_no_bucket() {
+        let limiter = RateLimiter::unlimited().account("acme", Quota::per_minute(1));
+        let now = Instant::now();
+        for account in ["a", "b", "c"] {
+            assert_eq!(limiter.reserve_at(Some(account), now), Duration::ZERO);
+        }
+        limiter.reserve_at(Some("acme"), now);
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 1);
+
+        limiter.throttled_at(Some("a"), &HeaderMap::new(), now);
+        assert_eq!(limiter.reserve_at(Some("a"), now), DEFAULT_PAUSE);
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 2);
+        assert_eq!(
+            limiter.reserve_at(Some("a"), now + DEFAULT_PAUSE),
+            Duration::ZERO
+        );
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_throttled_pauses_account() {
+        let limiter = RateLimiter::new(Quota::per_second(10));
+        let now = Instant::now();
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("2"));
+        limiter.throttled_at(Some("acme"), &headers, now);
+
+        assert_eq!(limiter.reserve_at(Some("acme"), now), ms(2100));
+        assert_eq!(limiter.reserve_at(Some("acme"), now), ms(2200));
+        assert_eq!(limiter.reserve_at(Some("other"), now), Duration::ZERO);
+
+        let unlimited = RateLimiter::unlimited();
+        unlimited.throttled_at(None, &HeaderMap::new(), now);
+        assert_eq!(unlimited.reserve_at(None, now), DEFAULT_PAUSE);
+        assert_eq!(
+            unlimited.reserve_at(None, now + DEFAULT_PAUSE),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_parse_reset() {
+        assert_eq!(parse_reset(Some("30")), Some(Duration::from_secs(30)));
+        let in_a_minute = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 60;
+        let pause = parse_reset(Some(&in_a_minute.to_string())).unwrap();
+        assert!(pause > Duration::from_secs(58) && pause <= Duration::from_secs(60));
+        assert_eq!(parse_reset(Some("soon")), None);
+    }
+}
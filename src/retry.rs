@@ -0,0 +1,286 @@
+//! Retry policy for transient download failures
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime};
+
+use crate::ImageChartsError;
+
+/// Retry policy applied by the async and blocking download paths
+///
+/// The default policy makes a single attempt. Validation errors (responses carrying an
+/// `x-ic-error-validation` header) are never retried.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ ImageCharts, RetryPolicy };
+/// use std::time::Duration;
+///
+/// let chart = ImageCharts::builder()
+///     .retry(RetryPolicy {
+///         max_attempts: 4,
+///         initial_backoff: Duration::from_millis(200),
+///         ..Default::default()
+///     })
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the exponential backoff
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after each retry
+    pub multiplier: f64,
+    /// Randomize each delay between half and all of its value
+    pub jitter: bool,
+    /// HTTP status codes worth retrying
+    pub retryable_status_codes: Vec<u16>,
+    /// `x-ic-error-code` values worth retrying, whatever the status code
+    pub retryable_error_codes: Vec<String>,
+    /// Retry connection failures and timeouts
+    pub retry_transport_errors: bool,
+    /// Wait at least as long as the `Retry-After` response header asks
+    pub respect_retry_after: bool,
+    /// Longest wait accepted from a `Retry-After` header, longer ones are cut down to it
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+            retryable_status_codes: vec![408, 429, 500, 502, 503, 504],
+            retryable_error_codes: Vec::new(),
+            retry_transport_errors: true,
+            respect_retry_after: true,
+            max_retry_after: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A failed download attempt
+#[derive(Debug)]
+pub(crate) enum Failure {
+    /// The request could not be sent or its body could not be read
+    Transport(ImageChartsError),
+    /// The API answered with a non-success status
+    Response {
+        error: ImageChartsError,
+        validation: bool,
+        retry_after: Option<Duration>,
+    },
+}
+
+impl Failure {
+    pub(crate) fn into_error(self) -> ImageChartsError {
+        match self {
+            Failure::Transport(error) | Failure::Response { error, .. } => error,
+        }
+    }
+}
+
+/// Parse a `Retry-After` header, either seconds to wait or an HTTP date
+pub(crate) fn parse_retry_after(value: Option<&str>) -> Option<Duration> {
+    let value = value?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    // A date already past asks for no wait
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+impl RetryPolicy {
+    /// Policy making a single attempt
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Default backoff settings with up to `max_attempts` attempts
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Backoff before retry number `retry` (starting at 1), jitter excluded
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .max(1.0)
+            .powi(retry.saturating_sub(1) as i32);
+        self.initial_backoff
+            .mul_f64(factor.min(u32::MAX as f64))
+            .min(self.max_backoff)
+    }
+
+    fn is_retryable(&self, failure: &Failure) -> bool {
+        match failure {
+            Failure::Transport(_) => self.retry_transport_errors,
+            Failure::Response {
+                validation: true, ..
+            } => false,
+            Failure::Response { error, .. } => {
+                error
+                    .status_code
+                    .is_some_and(|status| self.retryable_status_codes.contains(&status))
+                    || error
+                        .code
+                        .as_ref()
+                        .is_some_and(|code| self.retryable_error_codes.contains(code))
+            }
+        }
+    }
+
+    /// Delay to wait before the next attempt, `None` when `failure` must be returned
+    pub(crate) fn delay(&self, attempt: u32, failure: &Failure) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.is_retryable(failure) {
+            return None;
+        }
+
+        let backoff = self.backoff(attempt);
+        let mut delay = if self.jitter {
+            let random = RandomState::new().build_hasher().finish();
+            backoff / 2 + backoff.mul_f64((random % 1_000_000) as f64 / 2_000_000.0)
+        } else {
+            backoff
+        };
+        if let Failure::Response {
+            retry_after: Some(retry_after),
+            ..
+        } = failure
+        {
+            if self.respect_retry_after {
+                delay = delay.max((*retry_after).min(self.max_retry_after));
+            }
+        }
+        Some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageChartsErrorKind;
+
+    fn response(status: u16, code: Option<&str>, validation: bool) -> Failure {
+        let mut error = ImageChartsError::new(ImageChartsErrorKind::Http { status }, "failure")
+            .with_status(status);
+        if let Some(code) = code {
+            error = error.with_code(code);
+        }
+        Failure::Response {
+            error,
+            validation,
+            retry_after: None,
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_policy_never_retries() {
+        let failure = response(503, None, false);
+        assert_eq!(RetryPolicy::default().delay(1, &failure), None);
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = RetryPolicy {
+            max_backoff: Duration::from_millis(1500),
+            ..policy()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(3), Duration::from_millis(1500));
+        assert_eq!(policy.backoff(100), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_retryable_failures() {
+        let policy = RetryPolicy {
+            retryable_error_codes: vec!["IC_RATE_LIMITED".to_string()],
+            ..policy()
+        };
+        let delay = Some(Duration::from_millis(500));
+        assert_eq!(policy.delay(1, &response(503, None, false)), delay);
+        assert_eq!(
+            policy.delay(1, &response(400, Some("IC_RATE_LIMITED"), false)),
+            delay
+        );
+        assert_eq!(
+            policy.delay(
+                1,
+                &Failure::Transport(ImageChartsError::new(
+                    ImageChartsErrorKind::Timeout,
+                    "timeout"
+                ))
+            ),
+            delay
+        );
+        assert_eq!(policy.delay(1, &response(404, None, false)), None);
+        assert_eq!(policy.delay(1, &response(503, None, true)), None);
+        assert_eq!(policy.delay(3, &response(503, None, false)), None);
+    }
+
+    #[test]
+    fn test_retry_after_and_jitter() {
+        let failure = Failure::Response {
+            error: ImageChartsError::new(ImageChartsErrorKind::Http { status: 429 }, "busy")
+                .with_status(429),
+            validation: false,
+            retry_after: parse_retry_after(Some(" 3 ")),
+        };
+        assert_eq!(policy().delay(1, &failure), Some(Duration::from_secs(3)));
+
+        let jittered = RetryPolicy {
+            jitter: true,
+            ..policy()
+        };
+        let delay = jittered.delay(1, &response(503, None, false)).unwrap();
+        assert!(delay >= Duration::from_millis(250) && delay <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_retry_after_dates_and_cap() {
+        let in_an_hour = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(3600));
+        let wait = parse_retry_after(Some(&in_an_hour)).unwrap();
+        assert!(wait > Duration::from_secs(3590) && wait <= Duration::from_secs(3600));
+        assert_eq!(
+            parse_retry_after(Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after(Some("tomorrow")), None);
+
+        let failure = Failure::Response {
+            error: ImageChartsError::new(ImageChartsErrorKind::Http { status: 503 }, "down")
+                .with_status(503),
+            validation: false,
+            retry_after: parse_retry_after(Some("86400")),
+        };
+        assert_eq!(policy().delay(1, &failure), Some(Duration::from_secs(60)));
+        let patient = RetryPolicy {
+            max_retry_after: Duration::from_secs(120),
+            ..policy()
+        };
+        assert_eq!(patient.delay(1, &failure), Some(Duration::from_secs(120)));
+    }
+}
@@ -0,0 +1,172 @@
+//! Verification of enterprise `ichm` signatures
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::url::parse_chart_url;
+use crate::{ImageCharts, ImageChartsError, ImageChartsErrorKind};
+
+/// Reason why a signed chart URL was rejected
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignatureError {
+    /// The URL could not be parsed
+    #[error("Invalid chart URL: {0}")]
+    InvalidUrl(String),
+    /// No secret is configured to check the signature against
+    #[error("No secret configured to verify the ichm signature")]
+    MissingSecret,
+    /// The `icac` (account id) parameter is missing
+    #[error("Missing icac (account id) parameter")]
+    MissingAccountId,
+    /// The `ichm` (signature) parameter is missing
+    #[error("Missing ichm signature parameter")]
+    MissingSignature,
+    /// The `ichm` signature does not match the query
+    #[error("ichm signature does not match the chart parameters")]
+    Mismatch,
+}
+
+impl From<SignatureError> for ImageChartsError {
+    fn from(err: SignatureError) -> Self {
+        ImageChartsError::new(ImageChartsErrorKind::Signing, err.to_string()).with_source(err)
+    }
+}
+
+/// Check the `ichm` signature of a chart URL against an enterprise secret
+///
+/// The HMAC-SHA256 is recomputed over the canonical sorted query, exactly as
+/// [`ImageCharts::to_url`] builds it, and compared in constant time.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ verify_signature, ImageCharts, SignatureError };
+///
+/// let url = ImageCharts::with_secret("plop")
+///     .cht("p")
+///     .chs("100x100")
+///     .icac("test_fixture")
+///     .to_url();
+///
+/// assert!(verify_signature(&url, "plop").is_ok());
+/// assert_eq!(verify_signature(&url, "wrong"), Err(SignatureError::Mismatch));
+/// ```
+pub fn verify_signature(url: &str, secret: &str) -> Result<(), SignatureError> {
+    let (config, mut query) =
+        parse_chart_url(url).map_err(|e| SignatureError::InvalidUrl(e.message))?;
+    let signature = query
+        .remove("ichm")
+        .ok_or(SignatureError::MissingSignature)?;
+    if !query.contains_key("icac") {
+        return Err(SignatureError::MissingAccountId);
+    }
+
+    let chart = ImageCharts { config, query };
+    let expected = hex::decode(signature).map_err(|_| SignatureError::Mismatch)?;
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(chart.canonical_query().as_bytes());
+    mac.verify_slice(&expected)
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+impl ImageCharts {
+    /// Check the `ichm` signature of a chart URL against the configured secret
+    ///
+    /// See [`verify_signature`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image_charts::ImageCharts;
+    ///
+    /// let proxy = ImageCharts::with_secret("plop");
+    /// let url = ImageCharts::with_secret("plop")
+    ///     .cht("p")
+    ///     .chs("100x100")
+    ///     .icac("test_fixture")
+    ///     .to_url();
+    ///
+    /// assert!(proxy.verify_signature(&url).is_ok());
+    /// ```
+    pub fn verify_signature(&self, url: &str) -> Result<(), SignatureError> {
+        match self.config.secret.as_deref() {
+            Some(secret) if !secret.is_empty() => verify_signature(url, secret),
+            _ => Err(SignatureError::MissingSecret),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_url() -> String {
+        ImageCharts::with_secret("plop")
+            .cht("p")
+            .chd("t:1,2,3")
+            .chs("100x100")
+            .icac("test_fixture")
+            .to_url()
+    }
+
+    #[test]
+    fn test_verify_signature_valid() {
+        assert_eq!(verify_signature(&signed_url(), "plop"), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_signature_errors() {
+        let url = signed_url();
+        assert_eq!(
+            verify_signature(&url, "other"),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify_signature(&url.replace("t%3A1", "t%3A9"), "plop"),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify_signature(&format!("{}zz", url), "plop"),
+            Err(SignatureError::Mismatch)
+        );
+
+        let unsigned = ImageCharts::new().cht("p").icac("test_fixture").to_url();
+        assert_eq!(
+            verify_signature(&unsigned, "plop"),
+            Err(SignatureError::MissingSignature)
+        );
+        let without_account = url.replace("&icac=test_fixture", "");
+        assert_eq!(
+            verify_signature(&without_account, "plop"),
+            Err(SignatureError::MissingAccountId)
+        );
+        let url_start = url.find('?').unwrap() + 1;
+        let tampered = format!("{}chs=999x999&{}", &url[..url_start], &url[url_start..]);
+        assert_eq!(
+            verify_signature(&tampered, "plop"),
+            Err(SignatureError::InvalidUrl(
+                "Repeated parameter \"chs\"".to_string()
+            ))
+        );
+        assert!(matches!(
+            verify_signature("not a url", "plop"),
+            Err(SignatureError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_signature_method() {
+        let url = signed_url();
+        assert_eq!(
+            ImageCharts::with_secret("plop").verify_signature(&url),
+            Ok(())
+        );
+        assert_eq!(
+            ImageCharts::new().verify_signature(&url),
+            Err(SignatureError::MissingSecret)
+        );
+    }
+}
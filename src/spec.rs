@@ -0,0 +1,267 @@
+//! Serializable chart specifications, holding parameters without any configuration
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{ImageCharts, ImageChartsConfig, ImageChartsError, ImageChartsErrorKind};
+
+/// How [`ChartSpec::deserialize_with`] handles keys that are not chart parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum UnknownKeys {
+    /// Fail on the first unknown key, to catch typos
+    #[default]
+    Reject,
+    /// Keep unknown keys in [`ChartSpec::extra`] and send them as parameters
+    Preserve,
+}
+
+macro_rules! chart_spec {
+    ($($(#[$doc:meta])* $name:ident,)*) => {
+        /// Chart parameters as plain data, to store charts in configuration files or send
+        /// them between services
+        ///
+        /// Every parameter method of [`ImageCharts`] has a field of the same name. The
+        /// configuration, secret included, is never part of a spec and neither is the
+        /// `ichm` signature: both come from the configuration the spec is turned into a
+        /// chart with. Unset parameters are skipped when serializing.
+        ///
+        /// Deserializing with the `Deserialize` impl preserves unknown keys; use
+        /// [`ChartSpec::deserialize_with`] or [`ChartSpec::from_json`] to choose.
+        ///
+        /// # Example
+        ///
+        /// ```rust
+        /// use image_charts::{ ChartSpec, ImageCharts, UnknownKeys };
+        ///
+        /// let spec = ChartSpec::from_json(
+        ///     r#"{ "cht": "p", "chd": "t:60,40", "chs": "100x100" }"#,
+        ///     UnknownKeys::Reject,
+        /// ).unwrap();
+        /// let chart = ImageCharts::from(spec.clone());
+        /// assert_eq!(chart.to_url(), "https://image-charts.com/chart?chd=t%3A60%2C40&chs=100x100&cht=p");
+        /// assert_eq!(ChartSpec::from(&chart), spec);
+        ///
+        /// let typo = ChartSpec::from_json(r#"{ "cht": "p", "chtitle": "Sales" }"#, UnknownKeys::Reject);
+        /// assert!(typo.unwrap_err().message.contains("chtitle"));
+        /// ```
+        #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+        pub struct ChartSpec {
+            $(
+                $(#[$doc])*
+                #[serde(default, skip_serializing_if = "Option::is_none")]
+                pub $name: Option<String>,
+            )*
+            /// Parameters without a named field, kept with [`UnknownKeys::Preserve`]
+            #[serde(flatten)]
+            pub extra: BTreeMap<String, String>,
+        }
+
+        impl ChartSpec {
+            /// Names of the parameters having a named field
+            pub const PARAMETERS: &'static [&'static str] = &[$(stringify!($name)),*];
+
+            pub(crate) fn from_query(mut query: HashMap<String, String>) -> Self {
+                query.remove("ichm");
+                Self {
+                    $($name: query.remove(stringify!($name)),)*
+                    extra: query.into_iter().collect(),
+                }
+            }
+
+            pub(crate) fn into_query(self) -> HashMap<String, String> {
+                let mut query: HashMap<String, String> = self.extra.into_iter().collect();
+                $(
+                    if let Some(value) = self.$name {
+                        query.insert(stringify!($name).to_string(), value);
+                    }
+                )*
+                query.remove("ichm");
+                query
+            }
+        }
+    };
+}
+
+chart_spec! {
+    /// Chart type
+    cht,
+    /// Chart data
+    chd,
+    /// Data scaling
+    chds,
+    /// QR code data encoding
+    choe,
+    /// QR code error correction level and margin
+    chld,
+    /// Axis ranges
+    chxr,
+    /// Output format
+    chof,
+    /// Chart size, `<width>x<height>`
+    chs,
+    /// Legend labels
+    chdl,
+    /// Legend text color and font size
+    chdls,
+    /// Grid lines
+    chg,
+    /// Series colors
+    chco,
+    /// Chart title
+    chtt,
+    /// Title color and font size
+    chts,
+    /// Visible axes
+    chxt,
+    /// Custom axis labels
+    chxl,
+    /// Axis label positions
+    chxp,
+    /// Axis label styles
+    chxs,
+    /// Markers
+    chm,
+    /// Line styles
+    chls,
+    /// Data labels
+    chl,
+    /// Data label positions
+    chlps,
+    /// Chart margins
+    chma,
+    /// Legend position and order
+    chdlp,
+    /// Background fills
+    chf,
+    /// Bar corner radius
+    chbr,
+    /// Animation duration and easing
+    chan,
+    /// Doughnut inside label
+    chli,
+    /// Enterprise account id
+    icac,
+    /// Font family
+    icff,
+    /// Font size
+    icfs,
+    /// Locale used to format numbers and dates
+    iclocale,
+    /// Retina mode
+    icretina,
+    /// QR code background color
+    icqrb,
+    /// QR code foreground color
+    icqrf,
+}
+
+impl ChartSpec {
+    /// Deserialize a spec, handling unknown keys according to `unknown`
+    ///
+    /// Works with any serde format, such as YAML:
+    ///
+    /// ```rust,ignore
+    /// let spec = ChartSpec::deserialize_with(
+    ///     serde_yaml::Deserializer::from_str(&yaml),
+    ///     UnknownKeys::Reject,
+    /// )?;
+    /// ```
+    pub fn deserialize_with<'de, D>(deserializer: D, unknown: UnknownKeys) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let spec = Self::deserialize(deserializer)?;
+        match spec.extra.keys().next() {
+            Some(key) if unknown == UnknownKeys::Reject => {
+                Err(D::Error::unknown_field(key, Self::PARAMETERS))
+            }
+            _ => Ok(spec),
+        }
+    }
+
+    /// Parse a JSON spec, handling unknown keys according to `unknown`
+    pub fn from_json(json: &str, unknown: UnknownKeys) -> Result<Self, ImageChartsError> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        Self::deserialize_with(&mut deserializer, unknown)
+            .and_then(|spec| deserializer.end().map(|()| spec))
+            .map_err(|e| {
+                ImageChartsError::new(
+                    ImageChartsErrorKind::InvalidConfig,
+                    format!("Invalid chart spec: {}", e),
+                )
+                .with_source(e)
+            })
+    }
+
+    /// Serialize the spec as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("chart specs only hold strings")
+    }
+
+    /// Chart with these parameters, rendered with `config`
+    pub fn into_chart(self, config: ImageChartsConfig) -> ImageCharts {
+        ImageCharts {
+            config,
+            query: self.into_query(),
+        }
+    }
+}
+
+impl From<&ImageCharts> for ChartSpec {
+    fn from(chart: &ImageCharts) -> Self {
+        Self::from_query(chart.query.clone())
+    }
+}
+
+impl From<ChartSpec> for ImageCharts {
+    fn from(spec: ChartSpec) -> Self {
+        spec.into_chart(ImageChartsConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chart_round_trip_skips_secret_and_signature() {
+        let chart = ImageCharts::with_secret("plop")
+            .cht("p")
+            .chs("100x100")
+            .icac("test_fixture")
+            .clone_with("chcustom", "1");
+        let spec = ChartSpec::from(&chart.clone_with("ichm", "forged"));
+        assert_eq!(spec.cht.as_deref(), Some("p"));
+        assert_eq!(spec.extra.get("chcustom").map(String::as_str), Some("1"));
+
+        let json = spec.to_json();
+        assert!(!json.contains("plop") && !json.contains("ichm") && !json.contains("chd"));
+
+        let config = chart.config.clone();
+        assert_eq!(spec.into_chart(config).to_url(), chart.to_url());
+    }
+
+    #[test]
+    fn test_unknown_keys_mode() {
+        let json = r#"{ "cht": "bvs", "chtitle": "Sales" }"#;
+        let err = ChartSpec::from_json(json, UnknownKeys::Reject).unwrap_err();
+        assert_eq!(err.kind, ImageChartsErrorKind::InvalidConfig);
+        assert!(err.message.contains("unknown field `chtitle`"));
+
+        let spec = ChartSpec::from_json(json, UnknownKeys::Preserve).unwrap();
+        assert_eq!(spec.extra.get("chtitle").map(String::as_str), Some("Sales"));
+        assert!(ImageCharts::from(spec).to_url().contains("chtitle=Sales"));
+    }
+
+    #[test]
+    fn test_from_json_rejects_invalid_values() {
+        assert!(ChartSpec::from_json(r#"{ "chs": 100 }"#, UnknownKeys::Preserve).is_err());
+        assert!(ChartSpec::from_json(r#"{ "cht": "p" } trailing"#, UnknownKeys::Preserve).is_err());
+        assert_eq!(
+            ChartSpec::from_json("{}", UnknownKeys::Reject).unwrap(),
+            ChartSpec::default()
+        );
+    }
+}
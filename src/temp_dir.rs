@@ -0,0 +1,42 @@
+//! Temporary directories for tests, removed on drop
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Empty directory under the system temp directory, removed on drop
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Fresh directory named after `name` and the process id
+    pub(crate) fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("image_charts-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub(crate) fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+
+    /// Names of the files in the directory, sorted
+    pub(crate) fn files(&self) -> Vec<String> {
+        let mut files = fs::read_dir(&self.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,274 @@
+//! Chart templates with `{{placeholders}}` filled in at render time
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::transport::io_error;
+use crate::{
+    ChartSpec, ImageCharts, ImageChartsConfig, ImageChartsError, ImageChartsErrorKind, UnknownKeys,
+};
+
+/// Chart parameters containing `{{name}}` placeholders, rendered into concrete charts
+///
+/// Placeholders may appear in any parameter, several times and surrounded by other text.
+/// Names are made of letters, digits, `_`, `-` and `.`, and spaces inside the braces are
+/// ignored: `{{ series.0 }}` is the `series.0` placeholder. Any other `{{` is kept as is.
+///
+/// A template serializes as the [`ChartSpec`] it holds, so layouts can be maintained in
+/// configuration files.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ ChartTemplate, ImageCharts };
+///
+/// let template = ChartTemplate::new(
+///     &ImageCharts::new()
+///         .cht("bvs")
+///         .chs("700x300")
+///         .chtt("{{title}}")
+///         .chd("t:{{series.0}}|{{series.1}}")
+///         .chco("3D7AB8,F5A623")
+///         .icff("Roboto"),
+/// );
+///
+/// let chart = template
+///     .render([("title", "Sales"), ("series.0", "10,20,30"), ("series.1", "5,15,25")])
+///     .unwrap();
+/// assert!(chart.to_url().contains("chtt=Sales"));
+///
+/// let err = template.render([("title", "Sales")]).unwrap_err();
+/// assert_eq!(err.message, "Missing template variables: series.0, series.1");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ChartTemplate {
+    spec: ChartSpec,
+}
+
+impl ChartTemplate {
+    /// Template made of the parameters of `chart`
+    pub fn new(chart: &ImageCharts) -> Self {
+        Self::from_spec(ChartSpec::from(chart))
+    }
+
+    /// Template made of the parameters of `spec`
+    pub fn from_spec(spec: ChartSpec) -> Self {
+        Self { spec }
+    }
+
+    /// Parse a JSON template, handling unknown keys according to `unknown`
+    pub fn from_json(json: &str, unknown: UnknownKeys) -> Result<Self, ImageChartsError> {
+        ChartSpec::from_json(json, unknown).map(Self::from_spec)
+    }
+
+    /// Read a JSON template file, handling unknown keys according to `unknown`
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        unknown: UnknownKeys,
+    ) -> Result<Self, ImageChartsError> {
+        let json = std::fs::read_to_string(path).map_err(io_error)?;
+        Self::from_json(&json, unknown)
+    }
+
+    /// Parameters of the template, placeholders included
+    pub fn spec(&self) -> &ChartSpec {
+        &self.spec
+    }
+
+    /// Names of every placeholder of the template
+    pub fn placeholders(&self) -> BTreeSet<String> {
+        self.spec
+            .clone()
+            .into_query()
+            .values()
+            .flat_map(|value| {
+                placeholders(value)
+                    .map(|(_, name)| name.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Chart with every placeholder replaced by its variable in `vars`
+    ///
+    /// Fails when a placeholder has no variable or a variable matches no placeholder.
+    pub fn render<I, K, V>(&self, vars: I) -> Result<ImageCharts, ImageChartsError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.render_with_config(vars, ImageChartsConfig::default())
+    }
+
+    /// Same as [`ChartTemplate::render`], returning a chart rendered with `config`
+    pub fn render_with_config<I, K, V>(
+        &self,
+        vars: I,
+        config: ImageChartsConfig,
+    ) -> Result<ImageCharts, ImageChartsError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let vars: BTreeMap<String, String> = vars
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+
+        let mut missing = BTreeSet::new();
+        let mut used = BTreeSet::new();
+        let query: HashMap<String, String> = self
+            .spec
+            .clone()
+            .into_query()
+            .into_iter()
+            .map(|(key, value)| {
+                let mut rendered = String::with_capacity(value.len());
+                let mut end = 0;
+                for (range, name) in placeholders(&value) {
+                    rendered.push_str(&value[end..range.start]);
+                    match vars.get(name) {
+                        Some(var) => {
+                            rendered.push_str(var);
+                            used.insert(name.to_string());
+                        }
+                        None => {
+                            missing.insert(name.to_string());
+                        }
+                    }
+                    end = range.end;
+                }
+                rendered.push_str(&value[end..]);
+                (key, rendered)
+            })
+            .collect();
+
+        let unused: Vec<&str> = vars
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !used.contains(*name))
+            .collect();
+        let mut problems = Vec::new();
+        if !missing.is_empty() {
+            let missing: Vec<String> = missing.into_iter().collect();
+            problems.push(format!(
+                "Missing template variables: {}",
+                missing.join(", ")
+            ));
+        }
+        if !unused.is_empty() {
+            problems.push(format!("Unused template variables: {}", unused.join(", ")));
+        }
+        if !problems.is_empty() {
+            return Err(ImageChartsError::new(
+                ImageChartsErrorKind::InvalidConfig,
+                problems.join("; "),
+            ));
+        }
+        Ok(ImageCharts { config, query })
+    }
+}
+
+impl From<ChartSpec> for ChartTemplate {
+    fn from(spec: ChartSpec) -> Self {
+        Self::from_spec(spec)
+    }
+}
+
+/// Byte range and name of each placeholder of `text`
+fn placeholders(text: &str) -> impl Iterator<Item = (std::ops::Range<usize>, &str)> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        while let Some(start) = text[offset..].find("{{").map(|i| offset + i) {
+            let Some(length) = text[start + 2..].find("}}") else {
+                offset = text.len();
+                return None;
+            };
+            let end = start + 2 + length + 2;
+            let name = text[start + 2..end - 2].trim();
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+            if valid {
+                offset = end;
+                return Some((start..end, name));
+            }
+            offset = start + 2;
+        }
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> ChartTemplate {
+        ChartTemplate::new(
+            &ImageCharts::new()
+                .cht("lc")
+                .chs("{{width}}x{{height}}")
+                .chtt("{{ title }} ({{width}}px)")
+                .chd("t:{{series.0}}")
+                .chl("{{not a placeholder}}|{{}}|{{"),
+        )
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let names: Vec<String> = template().placeholders().into_iter().collect();
+        assert_eq!(names, ["height", "series.0", "title", "width"]);
+    }
+
+    #[test]
+    fn test_render_substitutes_every_placeholder() {
+        let chart = template()
+            .render([
+                ("width", "400"),
+                ("height", "300"),
+                ("title", "Sales"),
+                ("series.0", "1,2,3"),
+            ])
+            .unwrap();
+        assert_eq!(chart.query["chs"], "400x300");
+        assert_eq!(chart.query["chtt"], "Sales (400px)");
+        assert_eq!(chart.query["chd"], "t:1,2,3");
+        assert_eq!(chart.query["chl"], "{{not a placeholder}}|{{}}|{{");
+        assert_eq!(chart.query["cht"], "lc");
+    }
+
+    #[test]
+    fn test_render_fails_on_missing_or_unused_variables() {
+        let err = template()
+            .render([("width", "400"), ("title", "Sales"), ("colour", "FF0000")])
+            .unwrap_err();
+        assert_eq!(err.kind, ImageChartsErrorKind::InvalidConfig);
+        assert_eq!(
+            err.message,
+            "Missing template variables: height, series.0; Unused template variables: colour"
+        );
+    }
+
+    #[test]
+    fn test_load_from_json() {
+        let json = r#"{ "cht": "p", "chs": "{{size}}", "chco": "3D7AB8" }"#;
+        let template = ChartTemplate::from_json(json, UnknownKeys::Reject).unwrap();
+        assert_eq!(serde_json::to_value(&template).unwrap()["chs"], "{{size}}");
+        let chart = template
+            .render_with_config([("size", "100x100")], ImageChartsConfig::default())
+            .unwrap();
+        assert_eq!(
+            chart.to_url(),
+            "https://image-charts.com/chart?chco=3D7AB8&chs=100x100&cht=p"
+        );
+
+        let err = ChartTemplate::from_file("/nonexistent/template.json", UnknownKeys::Reject);
+        assert_eq!(err.unwrap_err().kind, ImageChartsErrorKind::Io);
+    }
+}
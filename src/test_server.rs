@@ -0,0 +1,378 @@
+//! Local stand-in for the Image-Charts API, for integration tests
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::mock::MockResponse;
+use crate::transport::TransportRequest;
+use crate::url::parse_chart_url;
+use crate::{verify_signature, ImageCharts, ImageChartsConfig, OutputFormat};
+
+/// 1x1 transparent PNG served for PNG charts
+const PLACEHOLDER_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x64, 0x60, 0xf8, 0x5f,
+    0x0f, 0x00, 0x02, 0x87, 0x01, 0x80, 0xeb, 0x47, 0xba, 0x92, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45,
+    0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+/// 1x1 transparent GIF served for animated charts and `chof=.gif`
+const PLACEHOLDER_GIF: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x01, 0x44, 0x00, 0x3b,
+];
+
+#[derive(Debug)]
+struct ServerState {
+    addr: SocketAddr,
+    secret: Option<String>,
+    stopped: AtomicBool,
+    responses: Mutex<VecDeque<MockResponse>>,
+    requests: Mutex<Vec<TransportRequest>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl ServerState {
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+        let mut headers = HeaderMap::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(name.trim().as_bytes()),
+                    HeaderValue::from_str(value.trim()),
+                ) {
+                    headers.append(name, value);
+                }
+            }
+        }
+
+        let url = format!("http://{}{}", self.addr, target);
+        lock(&self.requests).push(TransportRequest {
+            url: url.clone(),
+            headers,
+        });
+        let scripted = lock(&self.responses).pop_front();
+        let response = scripted.unwrap_or_else(|| self.render(&url));
+        if response.failure.is_some() {
+            // Hang up without answering, as a crashed or unreachable server would
+            return Ok(());
+        }
+        write_response(&stream, &response)
+    }
+
+    /// Response of the API to a chart URL
+    fn render(&self, url: &str) -> MockResponse {
+        let (config, query) = match parse_chart_url(url) {
+            Ok(parsed) => parsed,
+            Err(e) => return MockResponse::error(400, "IC_INVALID_URL").body(e.message),
+        };
+        if config.pathname != ImageChartsConfig::default().pathname {
+            return MockResponse::new(404);
+        }
+        if let Some(secret) = &self.secret {
+            let signed = query.contains_key("icac") || query.contains_key("ichm");
+            if signed && verify_signature(url, secret).is_err() {
+                return MockResponse::error(403, "IC_INVALID_SIGNATURE");
+            }
+        }
+
+        let size = match query.get("chs").map(|chs| chs.split_once('x')) {
+            None => return MockResponse::validation(&["\"chs\" is required"]),
+            Some(None) => {
+                return MockResponse::validation(&["\"chs\" must be formatted as WIDTHxHEIGHT"])
+            }
+            Some(Some((width, height))) => (width.to_string(), height.to_string()),
+        };
+        let chart = ImageCharts { config, query };
+        match chart.inferred_output_format() {
+            OutputFormat::Png => MockResponse::image(OutputFormat::Png, PLACEHOLDER_PNG),
+            OutputFormat::Gif => MockResponse::image(OutputFormat::Gif, PLACEHOLDER_GIF),
+            OutputFormat::Svg => MockResponse::image(
+                OutputFormat::Svg,
+                format!(
+                    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\"/>",
+                    size.0, size.1
+                ),
+            ),
+        }
+    }
+}
+
+fn write_response(mut stream: &TcpStream, response: &MockResponse) -> io::Result<()> {
+    let reason = reqwest::StatusCode::from_u16(response.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown");
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!(
+            "{}: {}\r\n",
+            name,
+            value.to_str().unwrap_or_default()
+        ));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+/// HTTP server standing in for Image-Charts on an ephemeral local port
+///
+/// Charts are answered like the API does: `chs` is required, the `ichm` signature of
+/// charts carrying `icac` is checked when the server has a secret, and the body is a
+/// deterministic placeholder whose format follows `chof` and `chan` (1x1 PNG or GIF,
+/// SVG of the requested size). [`TestServer::push`] scripts the next responses instead,
+/// for instance to emit 429/5xx statuses or validation headers;
+/// [`MockResponse::failure`] closes the connection without answering.
+///
+/// Every request is recorded. The server stops when dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ ImageCharts, MockResponse, TestServer };
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let server = TestServer::with_secret("plop").unwrap();
+///     server.push(MockResponse::error(503, "IC_UNAVAILABLE"));
+///
+///     let chart = ImageCharts::builder()
+///         .protocol("http")
+///         .host(server.host())
+///         .port(server.port())
+///         .secret("plop")
+///         .build()
+///         .cht("p")
+///         .chd("t:60,40")
+///         .chs("100x100")
+///         .icac("test_fixture");
+///
+///     assert_eq!(chart.to_buffer().await.unwrap_err().status_code, Some(503));
+///     assert!(chart.to_buffer().await.unwrap().starts_with(b"\x89PNG"));
+///     assert_eq!(server.request_count(), 2);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TestServer {
+    state: Arc<ServerState>,
+    accept: Option<JoinHandle<()>>,
+}
+
+impl TestServer {
+    /// Start a server accepting unsigned charts
+    pub fn start() -> io::Result<Self> {
+        Self::spawn(None)
+    }
+
+    /// Start a server checking `ichm` signatures against `secret`
+    pub fn with_secret(secret: impl Into<String>) -> io::Result<Self> {
+        Self::spawn(Some(secret.into()))
+    }
+
+    fn spawn(secret: Option<String>) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let state = Arc::new(ServerState {
+            addr: listener.local_addr()?,
+            secret,
+            stopped: AtomicBool::new(false),
+            responses: Mutex::new(VecDeque::new()),
+            requests: Mutex::new(Vec::new()),
+        });
+
+        let accept_state = Arc::clone(&state);
+        let accept = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_state.stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let state = Arc::clone(&accept_state);
+                    thread::spawn(move || state.handle(stream));
+                }
+            }
+        });
+        Ok(Self {
+            state,
+            accept: Some(accept),
+        })
+    }
+
+    /// Host to configure with [`ImageChartsBuilder::host`](crate::ImageChartsBuilder::host)
+    pub fn host(&self) -> String {
+        self.state.addr.ip().to_string()
+    }
+
+    /// Port to configure with [`ImageChartsBuilder::port`](crate::ImageChartsBuilder::port)
+    pub fn port(&self) -> u16 {
+        self.state.addr.port()
+    }
+
+    /// Configuration pointing at this server, with its secret if any
+    pub fn config(&self) -> ImageChartsConfig {
+        ImageChartsConfig {
+            protocol: "http".to_string(),
+            host: self.host(),
+            port: self.port(),
+            secret: self.state.secret.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Queue the response of a future request, served instead of the rendered chart
+    pub fn push(&self, response: MockResponse) {
+        lock(&self.state.responses).push_back(response);
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<TransportRequest> {
+        lock(&self.state.requests).clone()
+    }
+
+    /// Number of requests received so far
+    pub fn request_count(&self) -> usize {
+        lock(&self.state.requests).len()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.state.stopped.store(true, Ordering::SeqCst);
+        // Wake the accept loop up so it sees the stop flag
+        let _ = TcpStream::connect(self.state.addr);
+        if let Some(accept) = self.accept.take() {
+            let _ = accept.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(server: &TestServer) -> ImageCharts {
+        ImageCharts::with_config(server.config())
+            .cht("p")
+            .chd("t:60,40")
+            .icac("test_fixture")
+    }
+
+    #[test]
+    fn test_render_placeholders() {
+        let server = TestServer::with_secret("plop").unwrap();
+        let state = &server.state;
+
+        let png = chart(&server).chs("100x100").to_url();
+        assert_eq!(state.render(&png).body, PLACEHOLDER_PNG);
+        let gif = chart(&server).chs("100x100").chan("1200").to_url();
+        assert_eq!(state.render(&gif).body, PLACEHOLDER_GIF);
+        let svg = chart(&server).chs("30x20").chof(OutputFormat::Svg).to_url();
+        assert_eq!(
+            state.render(&svg).body,
+            b"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"30\" height=\"20\"/>"
+        );
+    }
+
+    #[test]
+    fn test_render_rejections() {
+        let server = TestServer::with_secret("plop").unwrap();
+        let state = &server.state;
+
+        let unsized_chart = state.render(&chart(&server).to_url());
+        assert_eq!(unsized_chart.status, 400);
+        assert!(unsized_chart.headers.contains_key("x-ic-error-validation"));
+
+        let tampered = chart(&server)
+            .chs("100x100")
+            .to_url()
+            .replace("chs=100x100", "chs=200x200");
+        assert_eq!(state.render(&tampered).status, 403);
+
+        let signed = chart(&server).chs("100x100").to_url();
+        let (start, signature) = signed.split_once("ichm=").unwrap();
+        let rest = signature.find('&').map_or("", |i| &signature[i..]);
+        let forged = format!("{}ichm=00{}", start, rest);
+        assert_eq!(state.render(&forged).status, 403);
+        let repeated = signed.replace("chs=100x100", "chs=200x200&chs=100x100");
+        assert_eq!(state.render(&repeated).status, 400);
+        assert_eq!(state.render("http://h/other?chs=1x1").status, 404);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_client_against_server() {
+        let server = TestServer::with_secret("plop").unwrap();
+        server.push(MockResponse::error(429, "IC_RATE_LIMIT_EXCEEDED"));
+        let client = crate::ImageChartsClient::with_config(ImageChartsConfig {
+            retry: crate::RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::ZERO,
+                ..Default::default()
+            },
+            ..server.config()
+        })
+        .unwrap();
+
+        let buffer = client
+            .to_buffer(&chart(&server).chs("100x100"))
+            .await
+            .unwrap();
+        assert_eq!(buffer, PLACEHOLDER_PNG);
+        assert_eq!(server.request_count(), 2);
+
+        let err = client.to_buffer(&chart(&server)).await.unwrap_err();
+        assert_eq!(err.message, "\"chs\" is required");
+        assert_eq!(err.status_code, Some(400));
+
+        let requests = server.requests();
+        assert!(requests[0].url.contains("&ichm="));
+        assert!(requests[0].headers.contains_key("user-agent"));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_client_against_server() {
+        let server = TestServer::start().unwrap();
+        server.push(MockResponse::failure(
+            crate::ImageChartsErrorKind::Transport,
+        ));
+        let chart = chart(&server).chs("100x100");
+
+        let err = chart.to_buffer_blocking().unwrap_err();
+        assert_eq!(err.kind, crate::ImageChartsErrorKind::Transport);
+
+        let data_uri = chart
+            .chof(OutputFormat::Svg)
+            .to_data_uri_blocking()
+            .unwrap();
+        assert!(data_uri.starts_with("data:image/svg+xml,%3Csvg"));
+    }
+}
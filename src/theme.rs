@@ -0,0 +1,394 @@
+//! Named bundles of colors, font and margins applied across chart parameters
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::title::DEFAULT_TITLE_FONT_SIZE;
+use crate::transport::io_error;
+use crate::{Color, ImageCharts, ImageChartsError, ImageChartsErrorKind, SeriesColors};
+
+/// Margins around the chart area, in pixels, written to `chma`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Margins {
+    /// Left margin
+    pub left: u32,
+    /// Right margin
+    pub right: u32,
+    /// Top margin
+    pub top: u32,
+    /// Bottom margin
+    pub bottom: u32,
+}
+
+impl Margins {
+    /// Same margin on every side
+    pub fn uniform(margin: u32) -> Self {
+        Self {
+            left: margin,
+            right: margin,
+            top: margin,
+            bottom: margin,
+        }
+    }
+}
+
+/// Named bundle of a series palette, background, text color, font and margins
+///
+/// Applied with [`ImageCharts::theme`]. Built-in themes cover light and dark
+/// backgrounds, high contrast and colorblind-safe palettes; custom themes can be built
+/// in code or loaded from JSON files, where colors are hex codes or CSS names:
+///
+/// ```json
+/// {
+///   "name": "brand",
+///   "palette": ["#0B3D91", "FC3D21", "gray"],
+///   "background": "FFFFFF",
+///   "text_color": "1B1B1B",
+///   "font": "Roboto",
+///   "margins": { "left": 20, "right": 20, "top": 10, "bottom": 10 }
+/// }
+/// ```
+///
+/// Every field but `name` is optional, and the parameters of an unset field are left
+/// untouched.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ Axis, ImageCharts, Theme };
+///
+/// let chart = ImageCharts::new()
+///     .cht("bvs")
+///     .chd("t:10,20|30,40")
+///     .axis(Axis::left().font_size(12))
+///     .theme(&Theme::dark());
+///
+/// let url = chart.to_url();
+/// assert!(url.contains("chco=8AB4F8%2CF28B82"));
+/// assert!(url.contains("chf=bg%2Cs%2C202124"));
+/// assert!(url.contains("chxs=0%2CE8EAED%2C12"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    palette: Vec<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    background: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text_color: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    font: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    margins: Option<Margins>,
+}
+
+impl Theme {
+    /// Theme named `name` that changes nothing yet
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            palette: Vec::new(),
+            background: None,
+            text_color: None,
+            font: None,
+            margins: None,
+        }
+    }
+
+    /// Dark text on a white background (`light`)
+    pub fn light() -> Self {
+        Self::new("light")
+            .palette(Self::hex([
+                0x4E79A7, 0xF28E2B, 0xE15759, 0x76B7B2, 0x59A14F, 0xEDC948, 0xB07AA1, 0xFF9DA7,
+            ]))
+            .background(Color::WHITE)
+            .text_color(Color::rgb(0x33, 0x33, 0x33))
+    }
+
+    /// Light text and bright series on a dark gray background (`dark`)
+    pub fn dark() -> Self {
+        Self::new("dark")
+            .palette(Self::hex([
+                0x8AB4F8, 0xF28B82, 0xFDD663, 0x81C995, 0xFF8BCB, 0xC58AF9, 0x78D9EC, 0xFCAD70,
+            ]))
+            .background(Color::rgb(0x20, 0x21, 0x24))
+            .text_color(Color::rgb(0xE8, 0xEA, 0xED))
+    }
+
+    /// White text and saturated series on a black background (`high-contrast`)
+    pub fn high_contrast() -> Self {
+        Self::new("high-contrast")
+            .palette(Self::hex([
+                0xFFFF00, 0x00FFFF, 0xFF00FF, 0x00FF00, 0xFF8000, 0xFFFFFF,
+            ]))
+            .background(Color::BLACK)
+            .text_color(Color::WHITE)
+    }
+
+    /// Okabe-Ito palette, distinguishable with any color vision deficiency (`colorblind-safe`)
+    pub fn colorblind_safe() -> Self {
+        Self::new("colorblind-safe")
+            .palette(Self::hex([
+                0xE69F00, 0x56B4E9, 0x009E73, 0xF0E442, 0x0072B2, 0xD55E00, 0xCC79A7, 0x000000,
+            ]))
+            .background(Color::WHITE)
+            .text_color(Color::BLACK)
+    }
+
+    /// Built-in theme called `name`, such as `dark` or `colorblind-safe`
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Self::light()),
+            "dark" => Some(Self::dark()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "colorblind-safe" => Some(Self::colorblind_safe()),
+            _ => None,
+        }
+    }
+
+    /// Parse a JSON theme, rejecting unknown keys
+    pub fn from_json(json: &str) -> Result<Self, ImageChartsError> {
+        serde_json::from_str(json).map_err(|e| {
+            ImageChartsError::new(
+                ImageChartsErrorKind::InvalidConfig,
+                format!("Invalid theme: {}", e),
+            )
+            .with_source(e)
+        })
+    }
+
+    /// Read a JSON theme file, rejecting unknown keys
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ImageChartsError> {
+        let json = std::fs::read_to_string(path).map_err(io_error)?;
+        Self::from_json(&json)
+    }
+
+    /// Serialize the theme as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("themes only hold strings and numbers")
+    }
+
+    /// Name of the theme
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Series colors, in series order, for `chco`
+    pub fn palette(mut self, colors: impl IntoIterator<Item = Color>) -> Self {
+        self.palette = colors.into_iter().collect();
+        self
+    }
+
+    /// Background of the whole image, for `chf`
+    pub fn background(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    /// Color of the legend, title and axis labels, for `chdls`, `chts` and `chxs`
+    pub fn text_color(mut self, color: Color) -> Self {
+        self.text_color = Some(color);
+        self
+    }
+
+    /// Google Font family of every text, for `icff`
+    pub fn font(mut self, family: impl Into<String>) -> Self {
+        self.font = Some(family.into());
+        self
+    }
+
+    /// Margins around the chart area, for `chma`
+    pub fn margins(mut self, margins: Margins) -> Self {
+        self.margins = Some(margins);
+        self
+    }
+
+    fn hex<const N: usize>(colors: [u32; N]) -> Vec<Color> {
+        colors
+            .iter()
+            .map(|hex| {
+                let [_, r, g, b] = hex.to_be_bytes();
+                Color::rgb(r, g, b)
+            })
+            .collect()
+    }
+}
+
+/// Replace the comma separated field at `index` of `value`, appending it when missing
+fn with_field(value: &str, index: usize, field: &str) -> String {
+    let mut fields: Vec<&str> = value.split(',').collect();
+    match fields.get_mut(index) {
+        Some(existing) => *existing = field,
+        None => fields.push(field),
+    }
+    fields.join(",")
+}
+
+impl ImageCharts {
+    /// Apply the palette, background, text color, font and margins of `theme`
+    ///
+    /// Text colors and the background are merged into the `chdls`, `chts`, `chxs` and
+    /// `chf` values already set, keeping their other fields, so the theme is best applied
+    /// after [`ImageCharts::legend`], [`ImageCharts::title`] and [`ImageCharts::axis`],
+    /// which replace those parameters. Axis labels are colored for every axis of `chxt`.
+    pub fn theme(self, theme: &Theme) -> Self {
+        let mut chart = self;
+        if !theme.palette.is_empty() {
+            let palette: SeriesColors = theme.palette.iter().copied().collect();
+            chart = chart.clone_with("chco", palette);
+        }
+        if let Some(background) = theme.background {
+            let mut fills = vec![format!("bg,s,{}", background)];
+            if let Some(chf) = chart.query.get("chf") {
+                fills.extend(
+                    chf.split('|')
+                        .filter(|fill| !fill.is_empty() && !fill.starts_with("bg,"))
+                        .map(str::to_string),
+                );
+            }
+            chart = chart.clone_with("chf", fills.join("|"));
+        }
+        if let Some(color) = theme.text_color {
+            chart = chart.themed_text(&color.to_string());
+        }
+        if let Some(font) = &theme.font {
+            chart = chart.clone_with("icff", font.as_str());
+        }
+        if let Some(margins) = theme.margins {
+            let legend = chart
+                .query
+                .get("chma")
+                .and_then(|chma| chma.split_once('|'))
+                .map(|(_, legend)| format!("|{}", legend))
+                .unwrap_or_default();
+            let chma = format!(
+                "{},{},{},{}{}",
+                margins.left, margins.right, margins.top, margins.bottom, legend
+            );
+            chart = chart.clone_with("chma", chma);
+        }
+        chart
+    }
+
+    fn themed_text(self, color: &str) -> Self {
+        let chdls = match self.query.get("chdls") {
+            Some(chdls) => with_field(chdls, 0, color),
+            None => color.to_string(),
+        };
+        let chts = match self.query.get("chts") {
+            Some(chts) => with_field(chts, 0, color),
+            None => format!("{},{}", color, DEFAULT_TITLE_FONT_SIZE),
+        };
+        let axes = self
+            .query
+            .get("chxt")
+            .filter(|chxt| !chxt.is_empty())
+            .map_or(0, |chxt| chxt.split(',').count());
+        let mut styles: Vec<String> = self
+            .query
+            .get("chxs")
+            .map(|chxs| chxs.split('|').map(str::to_string).collect())
+            .unwrap_or_default();
+        for axis in 0..axes {
+            let index = axis.to_string();
+            let styled = styles.iter_mut().find(|style| {
+                let digits =
+                    style.len() - style.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                style[..digits] == index
+            });
+            match styled {
+                Some(style) => *style = with_field(style, 1, color),
+                None => styles.push(format!("{},{}", index, color)),
+            }
+        }
+
+        let chart = self.clone_with("chdls", chdls).clone_with("chts", chts);
+        if styles.is_empty() {
+            chart
+        } else {
+            chart.clone_with("chxs", styles.join("|"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Legend, Title};
+
+    #[test]
+    fn test_theme_merges_into_existing_parameters() {
+        let chart = ImageCharts::new()
+            .chs("100x100")
+            .chd("t:1,2|3,4")
+            .chxt("x,y,r")
+            .chxs("1N*f1*,FF0000,12|2N*p*")
+            .chf("b0,s,FF0000|bg,s,FFFFFF")
+            .chma("10,10,10,10|80,20")
+            .legend(Legend::new(["a", "b"]).unwrap().font_size(16))
+            .title(Title::new("Title").font_family("Lato"))
+            .theme(
+                &Theme::new("brand")
+                    .palette([Color::BLACK, Color::WHITE])
+                    .background(Color::rgb(0x11, 0x22, 0x33))
+                    .text_color(Color::rgb(0xAA, 0xBB, 0xCC))
+                    .font("Roboto")
+                    .margins(Margins::uniform(5)),
+            );
+        assert_eq!(chart.query["chco"], "000000,FFFFFF");
+        assert_eq!(chart.query["chf"], "bg,s,112233|b0,s,FF0000");
+        assert_eq!(chart.query["chdls"], "AABBCC,16");
+        assert_eq!(chart.query["chts"], "AABBCC,14,c,Lato");
+        assert_eq!(
+            chart.query["chxs"],
+            "1N*f1*,AABBCC,12|2N*p*,AABBCC|0,AABBCC"
+        );
+        assert_eq!(chart.query["icff"], "Roboto");
+        assert_eq!(chart.query["chma"], "5,5,5,5|80,20");
+        assert!(chart.validate().is_empty());
+    }
+
+    #[test]
+    fn test_builtin_themes() {
+        for name in ["light", "dark", "high-contrast", "colorblind-safe"] {
+            let theme = Theme::builtin(name).unwrap();
+            assert_eq!(theme.name(), name);
+            assert!(!theme.palette.is_empty());
+        }
+        assert_eq!(Theme::builtin("sepia"), None);
+
+        let chart = ImageCharts::new().theme(&Theme::colorblind_safe());
+        assert_eq!(
+            chart.query["chco"],
+            "E69F00,56B4E9,009E73,F0E442,0072B2,D55E00,CC79A7,000000"
+        );
+        assert_eq!(chart.query["chts"], "000000,14");
+        assert!(!chart.query.contains_key("chxs"));
+    }
+
+    #[test]
+    fn test_theme_json() {
+        let theme = Theme::from_json(
+            r##"{ "name": "brand", "palette": ["#0B3D91", "navy"], "text_color": "1B1B1B",
+                 "margins": { "left": 1, "right": 2, "top": 3, "bottom": 4 } }"##,
+        )
+        .unwrap();
+        assert_eq!(
+            theme.palette,
+            [Color::rgb(0x0B, 0x3D, 0x91), Color::rgb(0, 0, 0x80)]
+        );
+        assert_eq!(Theme::from_json(&theme.to_json()).unwrap(), theme);
+
+        let err = Theme::from_json(r#"{ "name": "brand", "colour": "red" }"#).unwrap_err();
+        assert_eq!(err.kind, ImageChartsErrorKind::InvalidConfig);
+        assert!(err
+            .message
+            .starts_with("Invalid theme: unknown field `colour`"));
+        let err = Theme::from_json(r#"{ "name": "brand", "background": "reddish" }"#).unwrap_err();
+        assert!(err.message.contains("Unknown color \"reddish\""));
+    }
+}
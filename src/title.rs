@@ -0,0 +1,230 @@
+//! Typed chart title (`chtt`, `chts`) and chart-wide font (`icff`, `icfs`)
+
+use std::fmt;
+
+use crate::{positional_fields, Color, ImageCharts};
+
+/// Title color the API defaults to
+const DEFAULT_TITLE_COLOR: &str = "000000";
+
+/// Title font size the API defaults to
+pub(crate) const DEFAULT_TITLE_FONT_SIZE: u32 = 14;
+
+/// Centered title: the API ignores the alignment of `chts` but reads the fields after it
+const DEFAULT_TITLE_ALIGNMENT: &str = "c";
+
+/// Style of a font
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FontStyle {
+    /// Upright (`normal`)
+    Normal,
+    /// Italic (`italic`)
+    Italic,
+}
+
+impl FontStyle {
+    /// Value of this style in `chts` and `icfs`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FontStyle::Normal => "normal",
+            FontStyle::Italic => "italic",
+        }
+    }
+}
+
+impl fmt::Display for FontStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Chart title: text, color, font size, font family and style
+///
+/// Applied with [`ImageCharts::title`]. Each line of the text becomes a line of the
+/// title, the API breaks lines on `|` so a `|` inside the text also starts a new line.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ Color, FontStyle, ImageCharts, Title };
+///
+/// let title = Title::new("Quarterly sales\nEMEA")
+///     .color(Color::rgb(0x33, 0x33, 0x33))
+///     .font_size(18)
+///     .font_family("Roboto")
+///     .font_style(FontStyle::Italic);
+/// let chart = ImageCharts::new().cht("bvs").title(title);
+///
+/// let url = chart.to_url();
+/// assert!(url.contains("chtt=Quarterly%20sales%7CEMEA"));
+/// assert!(url.contains("chts=333333%2C18%2Cc%2CRoboto%2Citalic"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Title {
+    text: String,
+    color: Option<Color>,
+    font_size: Option<u32>,
+    font_family: Option<String>,
+    font_style: Option<FontStyle>,
+}
+
+impl Title {
+    /// Title showing `text`, one title line per line of text
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            color: None,
+            font_size: None,
+            font_family: None,
+            font_style: None,
+        }
+    }
+
+    /// Color of the title
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Font size of the title, in pixels
+    pub fn font_size(mut self, size: u32) -> Self {
+        self.font_size = Some(size);
+        self
+    }
+
+    /// Google Font family of the title, overriding the chart-wide [`Font`]
+    pub fn font_family(mut self, family: impl Into<String>) -> Self {
+        self.font_family = Some(family.into());
+        self
+    }
+
+    /// Font style of the title, overriding the chart-wide [`Font`]
+    pub fn font_style(mut self, style: FontStyle) -> Self {
+        self.font_style = Some(style);
+        self
+    }
+
+    fn chtt(&self) -> String {
+        self.text.lines().collect::<Vec<_>>().join("|")
+    }
+
+    fn chts(&self) -> Option<String> {
+        // `chts` needs a font size along with the color
+        let font_size = self
+            .font_size
+            .or(self.color.map(|_| DEFAULT_TITLE_FONT_SIZE));
+        let default_font_size = DEFAULT_TITLE_FONT_SIZE.to_string();
+        positional_fields([
+            (
+                self.color.map(|color| color.to_string()),
+                DEFAULT_TITLE_COLOR,
+            ),
+            (font_size.map(|size| size.to_string()), &default_font_size),
+            (None, DEFAULT_TITLE_ALIGNMENT),
+            (self.font_family.clone(), ""),
+            (self.font_style.map(|style| style.to_string()), ""),
+        ])
+    }
+}
+
+/// Font of every text of the chart: title, legend, axis and data labels
+///
+/// Applied with [`ImageCharts::font`]. The family is a
+/// [Google Font](https://fonts.google.com) name.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ Font, FontStyle, ImageCharts };
+///
+/// let chart = ImageCharts::new()
+///     .cht("p")
+///     .font(Font::new("Open Sans").style(FontStyle::Italic));
+///
+/// let url = chart.to_url();
+/// assert!(url.contains("icff=Open%20Sans"));
+/// assert!(url.contains("icfs=italic"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    family: String,
+    style: Option<FontStyle>,
+}
+
+impl Font {
+    /// Google Font `family`, in its default style
+    pub fn new(family: impl Into<String>) -> Self {
+        Self {
+            family: family.into(),
+            style: None,
+        }
+    }
+
+    /// Style of the font
+    pub fn style(mut self, style: FontStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+}
+
+impl ImageCharts {
+    /// Set the title, replacing any `chtt` and `chts` already set
+    pub fn title(self, title: Title) -> Self {
+        let mut chart = self.clone_with("chtt", title.chtt());
+        match title.chts() {
+            Some(chts) => chart = chart.clone_with("chts", chts),
+            None => {
+                chart.query.remove("chts");
+            }
+        }
+        chart
+    }
+
+    /// Set the font of every text, replacing any `icff` and `icfs` already set
+    pub fn font(self, font: Font) -> Self {
+        let mut chart = self.clone_with("icff", font.family);
+        match font.style {
+            Some(style) => chart = chart.clone_with("icfs", style.as_str()),
+            None => {
+                chart.query.remove("icfs");
+            }
+        }
+        chart
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_lines() {
+        let chart = ImageCharts::new().title(Title::new("First line\r\nSecond\nThird"));
+        assert_eq!(chart.query["chtt"], "First line|Second|Third");
+        assert!(!chart.query.contains_key("chts"));
+    }
+
+    #[test]
+    fn test_color_comes_with_font_size() {
+        let title = Title::new("t").color(Color::WHITE);
+        assert_eq!(title.chts(), Some("FFFFFF,14".into()));
+        assert_eq!(title.font_size(20).chts(), Some("FFFFFF,20".into()));
+        assert_eq!(
+            Title::new("t").font_style(FontStyle::Italic).chts(),
+            Some("000000,14,c,,italic".into())
+        );
+    }
+
+    #[test]
+    fn test_title_font_overrides_chart_font() {
+        let chart = ImageCharts::new()
+            .chs("100x100")
+            .icfs("italic")
+            .font(Font::new("Open Sans"))
+            .title(Title::new("Sales").font_family("Lato"));
+        assert_eq!(chart.query["icff"], "Open Sans");
+        assert!(!chart.query.contains_key("icfs"));
+        assert_eq!(chart.query["chts"], "000000,14,c,Lato");
+        assert!(chart.validate().is_empty());
+    }
+}
@@ -0,0 +1,454 @@
+//! HTTP transports used by the clients to reach the Image-Charts API
+
+use std::fmt;
+use std::future::Future;
+use std::io::{Read, Write};
+use std::pin::Pin;
+
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+use crate::ImageChartsConfig;
+use crate::{ImageChartsError, ImageChartsErrorKind};
+
+/// Boxed future returned by [`Transport::send`]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub(crate) fn transport_error(e: reqwest::Error) -> ImageChartsError {
+    let kind = if e.is_timeout() {
+        ImageChartsErrorKind::Timeout
+    } else {
+        ImageChartsErrorKind::Transport
+    };
+    let mut err = ImageChartsError::new(kind, e.to_string());
+    if let Some(status) = e.status() {
+        err = err.with_status(status.as_u16());
+    }
+    err.with_source(e)
+}
+
+fn read_error(e: std::io::Error) -> ImageChartsError {
+    let kind = if e.kind() == std::io::ErrorKind::TimedOut {
+        ImageChartsErrorKind::Timeout
+    } else {
+        ImageChartsErrorKind::Transport
+    };
+    ImageChartsError::new(kind, e.to_string()).with_source(e)
+}
+
+pub(crate) fn io_error(e: std::io::Error) -> ImageChartsError {
+    ImageChartsError::new(ImageChartsErrorKind::Io, e.to_string()).with_source(e)
+}
+
+/// Error of a blocking body read that panicked or was cancelled
+#[cfg(feature = "async")]
+fn join_error(e: tokio::task::JoinError) -> ImageChartsError {
+    ImageChartsError::new(ImageChartsErrorKind::Transport, e.to_string()).with_source(e)
+}
+
+fn client_error(e: reqwest::Error) -> ImageChartsError {
+    ImageChartsError::new(ImageChartsErrorKind::InvalidConfig, e.to_string()).with_source(e)
+}
+
+/// GET request sent to the API
+#[derive(Debug, Clone)]
+pub struct TransportRequest {
+    /// Chart URL, signature included
+    pub url: String,
+    /// Request headers, `User-Agent` included
+    pub headers: HeaderMap,
+}
+
+impl TransportRequest {
+    /// Request for `url` sent with the given `User-Agent`
+    pub(crate) fn new(url: String, user_agent: &str) -> Result<Self, ImageChartsError> {
+        let user_agent = HeaderValue::from_str(user_agent).map_err(|e| {
+            ImageChartsError::new(
+                ImageChartsErrorKind::InvalidConfig,
+                format!("Invalid user-agent \"{}\"", user_agent),
+            )
+            .with_source(e)
+        })?;
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, user_agent);
+        Ok(Self { url, headers })
+    }
+}
+
+/// Response of the API, whatever its status
+#[derive(Debug)]
+pub struct TransportResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers, `x-ic-error-*` ones included
+    pub headers: HeaderMap,
+    /// Response body, read lazily by the client
+    pub body: Body,
+}
+
+/// Body of a [`TransportResponse`]
+pub struct Body {
+    inner: BodyInner,
+}
+
+enum BodyInner {
+    Bytes(Vec<u8>),
+    Reader(Box<dyn Read + Send>),
+    #[cfg(feature = "async")]
+    Response(reqwest::Response),
+}
+
+impl Body {
+    /// Empty body
+    pub fn empty() -> Self {
+        Vec::new().into()
+    }
+
+    /// Body read from `reader`, on tokio's blocking thread pool when the async client
+    /// reads it
+    pub fn from_reader(reader: impl Read + Send + 'static) -> Self {
+        Self {
+            inner: BodyInner::Reader(Box::new(reader)),
+        }
+    }
+
+    /// Read the whole body
+    #[cfg(feature = "async")]
+    pub(crate) async fn bytes(self) -> Result<Vec<u8>, ImageChartsError> {
+        match self.inner {
+            BodyInner::Response(response) => response
+                .bytes()
+                .await
+                .map(|bytes| bytes.to_vec())
+                .map_err(transport_error),
+            BodyInner::Bytes(bytes) => Ok(bytes),
+            BodyInner::Reader(_) => tokio::task::spawn_blocking(move || self.bytes_blocking())
+                .await
+                .map_err(join_error)?,
+        }
+    }
+
+    /// Copy the body to `writer` chunk by chunk, then flush it
+    #[cfg(feature = "async")]
+    pub(crate) async fn write_to<W>(self, writer: &mut W) -> Result<(), ImageChartsError>
+    where
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        match self.inner {
+            BodyInner::Response(mut response) => {
+                while let Some(chunk) = response.chunk().await.map_err(transport_error)? {
+                    writer.write_all(&chunk).await.map_err(io_error)?;
+                }
+            }
+            BodyInner::Bytes(bytes) => writer.write_all(&bytes).await.map_err(io_error)?,
+            BodyInner::Reader(mut reader) => loop {
+                let (returned, chunk) = tokio::task::spawn_blocking(move || {
+                    let mut chunk = vec![0; CHUNK_SIZE];
+                    let read = read_chunk(&mut reader, &mut chunk).map(|read| {
+                        chunk.truncate(read);
+                        chunk
+                    });
+                    (reader, read)
+                })
+                .await
+                .map_err(join_error)?;
+                reader = returned;
+                let chunk = chunk?;
+                if chunk.is_empty() {
+                    break;
+                }
+                writer.write_all(&chunk).await.map_err(io_error)?;
+            },
+        }
+        writer.flush().await.map_err(io_error)
+    }
+
+    /// Read the whole body, blocking the current thread
+    pub(crate) fn bytes_blocking(self) -> Result<Vec<u8>, ImageChartsError> {
+        match self.inner {
+            BodyInner::Bytes(bytes) => Ok(bytes),
+            _ => {
+                let mut bytes = Vec::new();
+                self.write_to_blocking(&mut bytes)?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Copy the body to `writer` chunk by chunk, then flush it, blocking the current thread
+    pub(crate) fn write_to_blocking<W>(self, writer: &mut W) -> Result<(), ImageChartsError>
+    where
+        W: Write + ?Sized,
+    {
+        match self.inner {
+            BodyInner::Bytes(bytes) => writer.write_all(&bytes).map_err(io_error)?,
+            BodyInner::Reader(mut reader) => {
+                let mut chunk = [0; CHUNK_SIZE];
+                loop {
+                    let read = read_chunk(&mut reader, &mut chunk)?;
+                    if read == 0 {
+                        break;
+                    }
+                    writer.write_all(&chunk[..read]).map_err(io_error)?;
+                }
+            }
+            #[cfg(feature = "async")]
+            BodyInner::Response(_) => {
+                return Err(ImageChartsError::new(
+                    ImageChartsErrorKind::Transport,
+                    "An async response body cannot be read by a blocking client",
+                ))
+            }
+        }
+        writer.flush().map_err(io_error)
+    }
+}
+
+/// Size of the buffer used to copy bodies read from a `Read`
+const CHUNK_SIZE: usize = 16 * 1024;
+
+fn read_chunk(reader: &mut dyn Read, chunk: &mut [u8]) -> Result<usize, ImageChartsError> {
+    loop {
+        match reader.read(chunk) {
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            result => return result.map_err(read_error),
+        }
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.inner {
+            BodyInner::Bytes(bytes) => f.debug_tuple("Body").field(&bytes.len()).finish(),
+            _ => f.debug_tuple("Body").field(&"stream").finish(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self {
+            inner: BodyInner::Bytes(bytes),
+        }
+    }
+}
+
+impl From<&[u8]> for Body {
+    fn from(bytes: &[u8]) -> Self {
+        bytes.to_vec().into()
+    }
+}
+
+impl From<String> for Body {
+    fn from(body: String) -> Self {
+        body.into_bytes().into()
+    }
+}
+
+impl From<&str> for Body {
+    fn from(body: &str) -> Self {
+        body.as_bytes().into()
+    }
+}
+
+/// Sends the requests of an [`ImageChartsClient`](crate::ImageChartsClient)
+///
+/// Errors returned by `send` are transport failures (connection refused, timeout...):
+/// non-success statuses are regular responses, parsed and retried by the client.
+///
+/// # Example
+///
+/// ```rust
+/// use image_charts::{ BoxFuture, ImageChartsError, ReqwestTransport, Transport, TransportRequest, TransportResponse };
+///
+/// /// Log every chart URL before sending it
+/// struct Logging(ReqwestTransport);
+///
+/// impl Transport for Logging {
+///     fn send(&self, request: TransportRequest) -> BoxFuture<'_, Result<TransportResponse, ImageChartsError>> {
+///         println!("GET {}", request.url);
+///         self.0.send(request)
+///     }
+/// }
+/// ```
+#[cfg(feature = "async")]
+pub trait Transport: Send + Sync {
+    /// Send `request` and return the response, whatever its status
+    fn send(
+        &self,
+        request: TransportRequest,
+    ) -> BoxFuture<'_, Result<TransportResponse, ImageChartsError>>;
+}
+
+/// Sends the requests of a [`BlockingImageChartsClient`](crate::BlockingImageChartsClient)
+///
+/// Blocking counterpart of [`Transport`].
+#[cfg(feature = "blocking")]
+pub trait BlockingTransport: Send + Sync {
+    /// Send `request` and return the response, whatever its status
+    fn send(&self, request: TransportRequest) -> Result<TransportResponse, ImageChartsError>;
+}
+
+/// Default async [`Transport`], backed by a `reqwest` connection pool
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "async")]
+impl ReqwestTransport {
+    /// Transport applying the timeout of `config`
+    pub fn new(config: &ImageChartsConfig) -> Result<Self, ImageChartsError> {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(client_error)?;
+        Ok(Self { http })
+    }
+}
+
+#[cfg(feature = "async")]
+impl From<reqwest::Client> for ReqwestTransport {
+    fn from(http: reqwest::Client) -> Self {
+        Self { http }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Transport for ReqwestTransport {
+    fn send(
+        &self,
+        request: TransportRequest,
+    ) -> BoxFuture<'_, Result<TransportResponse, ImageChartsError>> {
+        Box::pin(async move {
+            let response = self
+                .http
+                .get(&request.url)
+                .headers(request.headers)
+                .send()
+                .await
+                .map_err(transport_error)?;
+            Ok(TransportResponse {
+                status: response.status().as_u16(),
+                headers: response.headers().clone(),
+                body: Body {
+                    inner: BodyInner::Response(response),
+                },
+            })
+        })
+    }
+}
+
+/// Default [`BlockingTransport`], backed by a `reqwest` blocking connection pool
+#[cfg(feature = "blocking")]
+#[derive(Debug, Clone)]
+pub struct BlockingReqwestTransport {
+    http: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "blocking")]
+impl BlockingReqwestTransport {
+    /// Transport applying the timeout of `config`
+    pub fn new(config: &ImageChartsConfig) -> Result<Self, ImageChartsError> {
+        let http = reqwest::blocking::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(client_error)?;
+        Ok(Self { http })
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl From<reqwest::blocking::Client> for BlockingReqwestTransport {
+    fn from(http: reqwest::blocking::Client) -> Self {
+        Self { http }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl BlockingTransport for BlockingReqwestTransport {
+    fn send(&self, request: TransportRequest) -> Result<TransportResponse, ImageChartsError> {
+        let response = self
+            .http
+            .get(&request.url)
+            .headers(request.headers)
+            .send()
+            .map_err(transport_error)?;
+        Ok(TransportResponse {
+            status: response.status().as_u16(),
+            headers: response.headers().clone(),
+            body: Body::from_reader(response),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_user_agent() {
+        let request = TransportRequest::new("https://h/chart".to_string(), "my-app/1.0").unwrap();
+        assert_eq!(request.headers[USER_AGENT], "my-app/1.0");
+
+        let err = TransportRequest::new("https://h/chart".to_string(), "bad\nagent").unwrap_err();
+        assert_eq!(err.kind, ImageChartsErrorKind::InvalidConfig);
+    }
+
+    #[test]
+    fn test_body_write_to_blocking() {
+        let mut image = Vec::new();
+        Body::from_reader(std::io::repeat(7).take(40_000))
+            .write_to_blocking(&mut image)
+            .unwrap();
+        assert_eq!(image, vec![7; 40_000]);
+
+        let mut full = [0; 2];
+        let err = Body::from("svg")
+            .write_to_blocking(&mut full.as_mut_slice())
+            .unwrap_err();
+        assert_eq!(err.kind, ImageChartsErrorKind::Io);
+    }
+
+    #[test]
+    fn test_body_bytes_blocking() {
+        assert_eq!(Body::from("svg").bytes_blocking().unwrap(), b"svg");
+        assert_eq!(
+            Body::from_reader(std::io::Cursor::new(vec![1, 2]))
+                .bytes_blocking()
+                .unwrap(),
+            vec![1, 2]
+        );
+        assert!(Body::empty().bytes_blocking().unwrap().is_empty());
+    }
+
+    /// Reader that fails unless it is read off the given (executor) thread
+    #[cfg(feature = "async")]
+    struct OffThread<R>(R, std::thread::ThreadId);
+
+    #[cfg(feature = "async")]
+    impl<R: Read> Read for OffThread<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if std::thread::current().id() == self.1 {
+                return Err(std::io::Error::other("read on the executor thread"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_body_reader_async() {
+        let executor = std::thread::current().id();
+        let body = Body::from_reader(OffThread(std::io::repeat(7).take(40_000), executor));
+        let mut image = Vec::new();
+        body.write_to(&mut image).await.unwrap();
+        assert_eq!(image, vec![7; 40_000]);
+
+        let body = Body::from_reader(OffThread(std::io::Cursor::new(vec![1, 2]), executor));
+        assert_eq!(body.bytes().await.unwrap(), vec![1, 2]);
+    }
+}
@@ -0,0 +1,203 @@
+//! Parsing of chart URLs back into [`ImageCharts`]
+
+use std::collections::HashMap;
+
+use crate::{ImageCharts, ImageChartsConfig, ImageChartsError, ImageChartsErrorKind};
+
+fn invalid(message: String) -> ImageChartsError {
+    ImageChartsError::new(ImageChartsErrorKind::InvalidConfig, message)
+}
+
+fn decode(component: &str) -> Result<String, ImageChartsError> {
+    urlencoding::decode(component)
+        .map(|decoded| decoded.into_owned())
+        .map_err(|e| invalid(format!("Invalid URL encoding: {}", e)))
+}
+
+/// Split a chart URL into its configuration (protocol, host, port, pathname) and
+/// decoded query parameters, `ichm` included
+///
+/// A parameter given twice is an error rather than last-wins, so a parameter prepended
+/// to a signed URL cannot shadow the signed one.
+pub(crate) fn parse_chart_url(
+    url: &str,
+) -> Result<(ImageChartsConfig, HashMap<String, String>), ImageChartsError> {
+    let url = url.split('#').next().unwrap_or_default();
+    let (protocol, rest) = url
+        .split_once("://")
+        .filter(|(protocol, _)| !protocol.is_empty())
+        .ok_or_else(|| invalid(format!("Invalid chart URL \"{}\"", url)))?;
+    let (location, query_string) = rest.split_once('?').unwrap_or((rest, ""));
+    let (authority, pathname) = match location.find('/') {
+        Some(index) => location.split_at(index),
+        None => (location, ""),
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => {
+            let port = port
+                .parse::<u16>()
+                .map_err(|_| invalid(format!("Invalid port \"{}\"", port)))?;
+            (host, port)
+        }
+        _ => {
+            let port = match protocol {
+                "https" => 443,
+                "http" => 80,
+                _ => {
+                    return Err(invalid(format!(
+                        "Missing port for protocol \"{}\"",
+                        protocol
+                    )))
+                }
+            };
+            (authority, port)
+        }
+    };
+    if host.is_empty() {
+        return Err(invalid(format!("Missing host in chart URL \"{}\"", url)));
+    }
+
+    let mut query = HashMap::new();
+    for pair in query_string.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = decode(key)?;
+        if query.contains_key(&key) {
+            return Err(invalid(format!("Repeated parameter \"{}\"", key)));
+        }
+        query.insert(key, decode(value)?);
+    }
+
+    let config = ImageChartsConfig {
+        protocol: protocol.to_string(),
+        host: host.to_string(),
+        port,
+        pathname: pathname.to_string(),
+        ..Default::default()
+    };
+    Ok((config, query))
+}
+
+impl ImageCharts {
+    /// Parse a chart URL, such as one produced by [`ImageCharts::to_url`], back into an
+    /// `ImageCharts` instance
+    ///
+    /// Protocol, host, port and pathname are inferred from the URL, every other setting
+    /// uses its default value. The `ichm` signature is stripped: use
+    /// [`ImageCharts::from_url_with_secret`] to verify it and sign the URL again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image_charts::ImageCharts;
+    ///
+    /// let url = ImageCharts::new().cht("p").chd("t:60,40").chs("100x100").to_url();
+    ///
+    /// let chart = ImageCharts::from_url(&url).unwrap();
+    /// assert_eq!(chart.to_url(), url);
+    ///
+    /// let resized = chart.chs("400x400").to_url();
+    /// assert!(resized.contains("chs=400x400"));
+    /// ```
+    pub fn from_url(url: &str) -> Result<Self, ImageChartsError> {
+        let (config, mut query) = parse_chart_url(url)?;
+        query.remove("ichm");
+        Ok(Self { config, query })
+    }
+
+    /// Parse a signed chart URL and check its `ichm` signature against `secret`
+    ///
+    /// The secret is kept in the configuration so [`ImageCharts::to_url`] signs the
+    /// (possibly edited) chart again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image_charts::ImageCharts;
+    ///
+    /// let url = ImageCharts::with_secret("plop")
+    ///     .cht("p")
+    ///     .chs("100x100")
+    ///     .icac("test_fixture")
+    ///     .to_url();
+    ///
+    /// let chart = ImageCharts::from_url_with_secret(&url, "plop").unwrap();
+    /// assert_eq!(chart.to_url(), url);
+    /// assert!(ImageCharts::from_url_with_secret(&url, "wrong").is_err());
+    /// ```
+    pub fn from_url_with_secret(
+        url: &str,
+        secret: impl Into<String>,
+    ) -> Result<Self, ImageChartsError> {
+        let mut chart = Self::from_url(url)?;
+        chart.config.secret = Some(secret.into());
+        chart.verify_signature(url)?;
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_url_round_trip() {
+        let url = ImageCharts::new()
+            .cht("bvs")
+            .chd("t:10,20|30,40")
+            .chl("Hello world|Ünïcode & co")
+            .chs("700x300")
+            .to_url();
+        assert_eq!(ImageCharts::from_url(&url).unwrap().to_url(), url);
+    }
+
+    #[test]
+    fn test_from_url_infers_config() {
+        let url = "http://localhost:8080/custom/chart?chs=100x100&cht=p";
+        let chart = ImageCharts::from_url(url).unwrap();
+        assert_eq!(chart.config.protocol, "http");
+        assert_eq!(chart.config.host, "localhost");
+        assert_eq!(chart.config.port, 8080);
+        assert_eq!(chart.config.pathname, "/custom/chart");
+        assert_eq!(chart.to_url(), url);
+
+        let chart = ImageCharts::from_url("http://[::1]/chart?cht=p").unwrap();
+        assert_eq!(chart.config.host, "[::1]");
+        assert_eq!(chart.config.port, 80);
+    }
+
+    #[test]
+    fn test_from_url_strips_signature() {
+        let url = ImageCharts::with_secret("plop")
+            .cht("p")
+            .chs("100x100")
+            .icac("test_fixture")
+            .to_url();
+        let chart = ImageCharts::from_url(&url).unwrap();
+        assert!(!chart.query.contains_key("ichm"));
+        assert_eq!(chart.config.secret, None);
+
+        let resigned = ImageCharts::from_url_with_secret(&url, "plop").unwrap();
+        assert_eq!(resigned.to_url(), url);
+    }
+
+    #[test]
+    fn test_from_url_rejects_tampered_signature() {
+        let url = ImageCharts::with_secret("plop")
+            .cht("p")
+            .chs("100x100")
+            .icac("test_fixture")
+            .to_url()
+            .replace("chs=100x100", "chs=200x200");
+        assert!(ImageCharts::from_url_with_secret(&url, "plop").is_err());
+    }
+
+    #[test]
+    fn test_from_url_invalid() {
+        assert!(ImageCharts::from_url("image-charts.com/chart?cht=p").is_err());
+        assert!(ImageCharts::from_url("ftp://image-charts.com/chart").is_err());
+        assert!(ImageCharts::from_url("https:///chart").is_err());
+        assert!(ImageCharts::from_url("https://h:99999/chart").is_err());
+        assert!(ImageCharts::from_url("https://h/chart?chl=%FF").is_err());
+    }
+}
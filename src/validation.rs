@@ -0,0 +1,417 @@
+//! Offline validation of chart parameters
+
+use serde::Deserialize;
+
+use crate::{ChartType, ImageCharts, ImageChartsError, ImageChartsErrorKind, OutputFormat};
+
+/// Maximum width or height of a chart, in pixels
+pub const MAX_CHART_DIMENSION: u32 = 999;
+
+/// Error code carried by errors produced by local validation
+const VALIDATION_ERROR_CODE: &str = "IC_VALIDATION_ERROR";
+
+/// Category of a [`ValidationError`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum ValidationErrorKind {
+    /// Reported by the Image-Charts API through the `x-ic-error-validation` header
+    #[default]
+    Api,
+    /// `chs` is missing
+    MissingSize,
+    /// `chs` is not `<width>x<height>` or exceeds [`MAX_CHART_DIMENSION`]
+    InvalidSize,
+    /// A color in `chco` or `chf` is not `RRGGBB` or `RRGGBBAA`
+    InvalidColor,
+    /// `chf` does not follow `<fill_type>,<fill_style>,...`
+    InvalidFill,
+    /// `chd` series count and `chdl` label count differ
+    SeriesLabelMismatch,
+    /// `chof` is not supported by the chart type
+    UnsupportedOutputFormat,
+    /// `chan` is not supported by the chart type
+    UnsupportedAnimation,
+    /// A `chm` marker refers to a series missing from `chd`
+    MarkerSeriesNotFound,
+}
+
+/// A single validation problem
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ValidationError {
+    /// Human readable description of the problem
+    pub message: String,
+    /// Query parameter the problem relates to, when known
+    #[serde(default)]
+    pub parameter: Option<String>,
+    /// Category of the problem
+    #[serde(skip)]
+    pub kind: ValidationErrorKind,
+}
+
+impl ValidationError {
+    fn new(
+        kind: ValidationErrorKind,
+        parameter: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            message: message.into(),
+            parameter: Some(parameter.into()),
+            kind,
+        }
+    }
+}
+
+fn is_valid_color(color: &str) -> bool {
+    matches!(color.len(), 6 | 8) && color.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn chd_series_count(chd: &str) -> Option<usize> {
+    let (prefix, series) = chd.split_at_checked(2)?;
+    let separator = match prefix {
+        "t:" | "a:" => '|',
+        "s:" | "e:" => ',',
+        _ => return None,
+    };
+    Some(series.split(separator).count())
+}
+
+impl ImageCharts {
+    /// Check the chart parameters without doing any network call
+    ///
+    /// Returns every problem found, an empty list meaning the chart looks valid.
+    /// Enable [`ImageChartsBuilder::validate`](crate::ImageChartsBuilder::validate) to run
+    /// these checks before each download.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use image_charts::{ ImageCharts, ValidationErrorKind };
+    ///
+    /// let errors = ImageCharts::new().cht("p").chd("t:1,2,3").validate();
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].kind, ValidationErrorKind::MissingSize);
+    /// assert_eq!(errors[0].message, "\"chs\" is required");
+    /// ```
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let chart_type = self.chart_type();
+
+        self.validate_size(&mut errors);
+        self.validate_colors(&mut errors);
+        self.validate_fills(&mut errors);
+        self.validate_markers(&mut errors);
+
+        if let (Some(chd), Some(chdl)) = (self.query.get("chd"), self.query.get("chdl")) {
+            let labels = chdl.split('|').count();
+            // Pie charts label their slices, not their series
+            let pie = chart_type.is_some_and(|t| t.is_pie());
+            if let Some(series) = chd_series_count(chd).filter(|_| !pie) {
+                if series != labels {
+                    errors.push(ValidationError::new(
+                        ValidationErrorKind::SeriesLabelMismatch,
+                        "chdl",
+                        format!(
+                            "\"chdl\" has {} label(s) but \"chd\" has {} series",
+                            labels, series
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let Some(chof) = self.query.get("chof") {
+            let supported = match self.output_format() {
+                Some(OutputFormat::Png | OutputFormat::Gif) => true,
+                Some(OutputFormat::Svg) => chart_type.is_none_or(|t| t.supports_svg()),
+                None => false,
+            };
+            if !supported {
+                errors.push(ValidationError::new(
+                    ValidationErrorKind::UnsupportedOutputFormat,
+                    "chof",
+                    format!(
+                        "\"chof\" value \"{}\" is not supported by chart type \"{}\"",
+                        chof,
+                        self.query.get("cht").map(String::as_str).unwrap_or("")
+                    ),
+                ));
+            }
+        }
+
+        if self.query.contains_key("chan") && chart_type.is_some_and(|t| !t.supports_animation()) {
+            errors.push(ValidationError::new(
+                ValidationErrorKind::UnsupportedAnimation,
+                "chan",
+                format!(
+                    "\"chan\" is not supported by chart type \"{}\"",
+                    self.query["cht"]
+                ),
+            ));
+        }
+
+        errors
+    }
+
+    fn validate_size(&self, errors: &mut Vec<ValidationError>) {
+        let Some(chs) = self.query.get("chs") else {
+            errors.push(ValidationError::new(
+                ValidationErrorKind::MissingSize,
+                "chs",
+                "\"chs\" is required",
+            ));
+            return;
+        };
+
+        let size = chs
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)));
+        let message = match size {
+            None => format!("\"chs\" must be <width>x<height>, got \"{}\"", chs),
+            Some((w, h)) if w == 0 || h == 0 => {
+                format!("\"chs\" width and height must be positive, got \"{}\"", chs)
+            }
+            Some((w, h)) if w > MAX_CHART_DIMENSION || h > MAX_CHART_DIMENSION => format!(
+                "\"chs\" width and height must not exceed {} pixels, got \"{}\"",
+                MAX_CHART_DIMENSION, chs
+            ),
+            Some(_) => return,
+        };
+        errors.push(ValidationError::new(
+            ValidationErrorKind::InvalidSize,
+            "chs",
+            message,
+        ));
+    }
+
+    fn validate_colors(&self, errors: &mut Vec<ValidationError>) {
+        let Some(chco) = self.query.get("chco") else {
+            return;
+        };
+        for color in chco.split([',', '|']) {
+            if !is_valid_color(color) {
+                errors.push(ValidationError::new(
+                    ValidationErrorKind::InvalidColor,
+                    "chco",
+                    format!("\"chco\" color \"{}\" must be RRGGBB or RRGGBBAA", color),
+                ));
+            }
+        }
+    }
+
+    fn validate_fills(&self, errors: &mut Vec<ValidationError>) {
+        let Some(chf) = self.query.get("chf") else {
+            return;
+        };
+        for fill in chf.split('|') {
+            let parts: Vec<&str> = fill.split(',').collect();
+            // Colors sit at fixed positions: right after the style for solid fills,
+            // and every other field after the angle for gradients and stripes
+            let colors: Vec<&str> = match parts.get(1) {
+                Some(&"s") if parts.len() == 3 => vec![parts[2]],
+                Some(&"lg") | Some(&"ls") if parts.len() >= 5 && parts.len() % 2 == 1 => {
+                    parts[3..].iter().step_by(2).copied().collect()
+                }
+                _ => {
+                    errors.push(ValidationError::new(
+                        ValidationErrorKind::InvalidFill,
+                        "chf",
+                        format!(
+                            "\"chf\" fill \"{}\" must be <fill_type>,s,<color> or <fill_type>,<lg|ls>,<angle>,<color>,<value>,...",
+                            fill
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            for color in colors.into_iter().filter(|c| !is_valid_color(c)) {
+                errors.push(ValidationError::new(
+                    ValidationErrorKind::InvalidColor,
+                    "chf",
+                    format!("\"chf\" color \"{}\" must be RRGGBB or RRGGBBAA", color),
+                ));
+            }
+        }
+    }
+
+    fn validate_markers(&self, errors: &mut Vec<ValidationError>) {
+        let (Some(chm), Some(chd)) = (self.query.get("chm"), self.query.get("chd")) else {
+            return;
+        };
+        let Some(series) = chd_series_count(chd) else {
+            return;
+        };
+        for marker in chm.split('|') {
+            let fields: Vec<&str> = marker.split(',').collect();
+            // Fills between series name both series, other markers a single one
+            let indices = match fields[0] {
+                "b" => fields.get(2..4).unwrap_or_default(),
+                _ => fields.get(2..3).unwrap_or_default(),
+            };
+            for index in indices.iter().filter_map(|i| i.parse::<usize>().ok()) {
+                if index >= series {
+                    errors.push(ValidationError::new(
+                        ValidationErrorKind::MarkerSeriesNotFound,
+                        "chm",
+                        format!(
+                            "\"chm\" marker \"{}\" refers to series {} but \"chd\" has {} series",
+                            marker, index, series
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Run [`ImageCharts::validate`] and turn problems into the error the API would return
+    pub(crate) fn check(&self) -> Result<(), ImageChartsError> {
+        let errors = self.validate();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let message = errors
+            .iter()
+            .map(|e| e.message.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Err(
+            ImageChartsError::new(ImageChartsErrorKind::Validation(errors), message)
+                .with_status(400)
+                .with_code(VALIDATION_ERROR_CODE),
+        )
+    }
+}
+
+impl ChartType {
+    /// Whether `chof=.svg` can be used with this chart type (QR codes and GraphViz)
+    pub fn supports_svg(&self) -> bool {
+        matches!(self, ChartType::QrCode | ChartType::GraphViz(_))
+    }
+
+    /// Whether `chan` GIF animation can be used with this chart type
+    pub fn supports_animation(&self) -> bool {
+        !matches!(self, ChartType::QrCode | ChartType::GraphViz(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(chart: &ImageCharts) -> Vec<ValidationErrorKind> {
+        chart.validate().into_iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn test_valid_chart() {
+        let chart = ImageCharts::new()
+            .cht("bvs")
+            .chs("999x999")
+            .chd("t:1,2|3,4")
+            .chdl("A|B")
+            .chco("FF0000,00FF0080")
+            .chf("bg,s,FFFFFF|c,lg,90,EA469EFF,1,03A9F47C,0.4")
+            .chan("1200");
+        assert!(chart.validate().is_empty());
+    }
+
+    #[test]
+    fn test_size() {
+        assert_eq!(
+            kinds(&ImageCharts::new().cht("p")),
+            vec![ValidationErrorKind::MissingSize]
+        );
+        for chs in ["100", "100x", "0x100", "1000x10", "axb"] {
+            assert_eq!(
+                kinds(&ImageCharts::new().chs(chs)),
+                vec![ValidationErrorKind::InvalidSize],
+                "{}",
+                chs
+            );
+        }
+    }
+
+    #[test]
+    fn test_colors_and_fills() {
+        let chart = ImageCharts::new()
+            .chs("100x100")
+            .chco("FF0000|GG0000,fff")
+            .chf("bg,s,red|c,lg,90,FFFFFF,0,000000|b0,x");
+        let errors = chart.validate();
+        let parameters: Vec<_> = errors
+            .iter()
+            .map(|e| (e.kind, e.parameter.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                (ValidationErrorKind::InvalidColor, "chco"),
+                (ValidationErrorKind::InvalidColor, "chco"),
+                (ValidationErrorKind::InvalidColor, "chf"),
+                (ValidationErrorKind::InvalidFill, "chf"),
+                (ValidationErrorKind::InvalidFill, "chf"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_series_label_mismatch() {
+        let chart = ImageCharts::new().chs("100x100").chdl("A|B|C");
+        assert!(chart.clone().chd("t:1,2|3,4|5,6").validate().is_empty());
+        assert!(chart.clone().chd("s:AB,CD,EF").validate().is_empty());
+        assert_eq!(
+            kinds(&chart.chd("a:1,2|3,4")),
+            vec![ValidationErrorKind::SeriesLabelMismatch]
+        );
+    }
+
+    #[test]
+    fn test_pie_labels_slices() {
+        let chart = ImageCharts::new().chs("100x100").chd("t:60,40").chdl("A|B");
+        for pie in ["p", "p3", "pd"] {
+            assert!(chart.clone().cht(pie).validate().is_empty());
+            assert!(chart.clone().cht(pie).check().is_ok());
+        }
+        assert_eq!(
+            kinds(&chart.cht("bvs")),
+            vec![ValidationErrorKind::SeriesLabelMismatch]
+        );
+    }
+
+    #[test]
+    fn test_output_format_and_animation() {
+        let chart = ImageCharts::new().chs("100x100");
+        assert!(chart.clone().cht("qr").chof(".svg").validate().is_empty());
+        assert!(chart
+            .clone()
+            .cht("gv:dot")
+            .chof(".svg")
+            .validate()
+            .is_empty());
+        assert_eq!(
+            kinds(&chart.clone().cht("p").chof(".svg")),
+            vec![ValidationErrorKind::UnsupportedOutputFormat]
+        );
+        assert_eq!(
+            kinds(&chart.clone().cht("p").chof(".jpg")),
+            vec![ValidationErrorKind::UnsupportedOutputFormat]
+        );
+        assert_eq!(
+            kinds(&chart.cht("qr").chan("1000")),
+            vec![ValidationErrorKind::UnsupportedAnimation]
+        );
+    }
+
+    #[test]
+    fn test_check_error_shape() {
+        let err = ImageCharts::new().cht("p").check().unwrap_err();
+        assert_eq!(err.message, "\"chs\" is required");
+        assert_eq!(err.status_code, Some(400));
+        assert_eq!(err.code.as_deref(), Some(VALIDATION_ERROR_CODE));
+        assert_eq!(err.validation_errors().len(), 1);
+        assert_eq!(
+            err.validation_errors()[0].kind,
+            ValidationErrorKind::MissingSize
+        );
+    }
+}